@@ -0,0 +1,527 @@
+//! blob-sim batch experiments
+//!
+
+// ============================================================================
+// Imports
+// ============================================================================
+
+use super::settings::{Settings, SettingsError};
+use super::simulation::{self, SimulationCore};
+use super::snapshot::{self, SnapshotError};
+use super::stats::{
+    csv_field, csv_number, GenerationStats, StatsFormat, StatsRecorder, TraitStats,
+};
+use rayon::prelude::*;
+use serde::Deserialize;
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    error, fmt, fs,
+    io::{self, BufWriter, Write},
+    path::Path,
+};
+
+// ============================================================================
+// Types
+// ============================================================================
+
+/// Settings values by their config file name
+pub type Parameters = BTreeMap<String, toml::Value>;
+
+// ============================================================================
+// Sweep
+// ============================================================================
+
+/// Parameter combinations of a batch, read from TOML
+///
+/// ```toml
+/// [grid]
+/// food_decay = [[100, 1, 25], [50, 1, 10]]
+/// "genes.speed.mutation" = [0.25, 0.5]
+///
+/// [[points]]
+/// start_blobs = 8
+///
+/// [[points]]
+/// start_blobs = 16
+/// tick_rate = 30
+/// ```
+///
+/// Every point is combined with every combination of the grid values, so the
+/// example above makes eight combinations. Without points the grid alone is
+/// used, without either there is a single combination of the base settings.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Sweep {
+    grid: BTreeMap<String, Vec<toml::Value>>,
+    points: Vec<Parameters>,
+}
+
+impl Sweep {
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Sweep, BatchError> {
+        toml::from_str(&fs::read_to_string(path)?)
+            .map_err(|e| BatchError::Settings(SettingsError::Parse(e)))
+    }
+
+    pub fn combinations(&self) -> Vec<Parameters> {
+        let mut combinations = if self.points.is_empty() {
+            vec![Parameters::new()]
+        } else {
+            self.points.clone()
+        };
+        for (key, values) in &self.grid {
+            combinations = combinations
+                .iter()
+                .flat_map(|combination| {
+                    values.iter().map(move |value| {
+                        let mut combination = combination.clone();
+                        combination.insert(key.clone(), value.clone());
+                        combination
+                    })
+                })
+                .collect();
+        }
+        combinations
+    }
+
+    /// Names of all swept parameters
+    pub fn keys(&self) -> Vec<String> {
+        let mut keys: BTreeSet<String> = self.grid.keys().cloned().collect();
+        for point in &self.points {
+            keys.extend(point.keys().cloned());
+        }
+        keys.into_iter().collect()
+    }
+}
+
+// ============================================================================
+// Batch
+// ============================================================================
+
+/// Replicate runs of every combination of a [`Sweep`]
+///
+/// Replicates of a combination are seeded with the seed plus the replicate
+/// index, wrapping around after [`MAX_SEED`](simulation::MAX_SEED), so all
+/// combinations see the same sequence of seeds.
+pub struct Batch {
+    keys: Vec<String>,
+    runs: Vec<BatchRun>,
+}
+
+pub struct BatchRun {
+    pub index: usize,
+    pub point: usize,
+    pub replicate: u32,
+    pub parameters: Parameters,
+    pub settings: Settings,
+}
+
+impl Batch {
+    pub fn new(base: &Settings, sweep: &Sweep, replicates: u32) -> Result<Batch, BatchError> {
+        let seed = base.seed().unwrap_or_else(simulation::random_seed);
+        let mut runs = vec![];
+        for (point, parameters) in sweep.combinations().into_iter().enumerate() {
+            let mut settings = base.with_overrides(&parameters)?;
+            let point_seed = settings.seed().unwrap_or(seed);
+            for replicate in 0..replicates {
+                settings.set_seed(Some(
+                    point_seed.wrapping_add(replicate as u64) & simulation::MAX_SEED,
+                ));
+                runs.push(BatchRun {
+                    index: runs.len(),
+                    point,
+                    replicate,
+                    parameters: parameters.clone(),
+                    settings: settings.clone(),
+                });
+            }
+        }
+        Ok(Batch {
+            keys: sweep.keys(),
+            runs,
+        })
+    }
+
+    #[inline(always)]
+    pub fn runs(&self) -> &[BatchRun] {
+        &self.runs
+    }
+
+    /// Runs all runs in parallel for `generations` generations
+    ///
+    /// With an `output` directory every run writes its files as
+    /// `run_<index>.*` (see [`run_headless`]), followed by `runs.csv` with the
+    /// final state of every run and `summary.csv` aggregating the replicates
+    /// of every combination.
+    pub fn run(
+        &self,
+        generations: u32,
+        output: Option<&Path>,
+        format: StatsFormat,
+    ) -> Result<Vec<RunSummary>, BatchError> {
+        let summaries = self
+            .runs
+            .par_iter()
+            .map(|run| {
+                let output = output.map(|dir| dir.join(format!("run_{:04}", run.index)));
+                let mut core = SimulationCore::new(run.settings.clone())?;
+                let summary =
+                    run_headless(&mut core, generations, output.as_deref(), format, |_| {})?;
+                match &summary.last {
+                    Some(stats) => println!("run {}: {}", run.index, stats),
+                    None => println!("run {}: no generations", run.index),
+                }
+                Ok(summary)
+            })
+            .collect::<Result<Vec<RunSummary>, BatchError>>()?;
+        if let Some(dir) = output {
+            self.write_runs(
+                &summaries,
+                &mut BufWriter::new(fs::File::create(dir.join("runs.csv"))?),
+            )?;
+            self.write_summary(
+                &summaries,
+                &mut BufWriter::new(fs::File::create(dir.join("summary.csv"))?),
+            )?;
+        }
+        Ok(summaries)
+    }
+
+    fn write_runs<W: Write>(&self, summaries: &[RunSummary], writer: &mut W) -> io::Result<()> {
+        let traits = trait_names(summaries);
+        write!(writer, "run,point,replicate,seed")?;
+        for key in &self.keys {
+            write!(writer, ",{}", csv_field(key))?;
+        }
+        write!(writer, ",generations,extinct,population")?;
+        for name in &traits {
+            write!(writer, ",{}", csv_field(&format!("{}_mean", name)))?;
+        }
+        writeln!(writer)?;
+        for (run, summary) in self.runs.iter().zip(summaries) {
+            write!(
+                writer,
+                "{},{},{},{}",
+                run.index,
+                run.point,
+                run.replicate,
+                run.settings.seed().unwrap_or_default()
+            )?;
+            for key in &self.keys {
+                write!(writer, ",{}", parameter(&run.parameters, key))?;
+            }
+            write!(
+                writer,
+                ",{},{},{}",
+                summary.generations,
+                summary.extinct.map(|g| g.to_string()).unwrap_or_default(),
+                summary.population()
+            )?;
+            for name in &traits {
+                write!(writer, ",{}", csv_number(summary.trait_mean(name)))?;
+            }
+            writeln!(writer)?;
+        }
+        writer.flush()
+    }
+
+    fn write_summary<W: Write>(&self, summaries: &[RunSummary], writer: &mut W) -> io::Result<()> {
+        let traits = trait_names(summaries);
+        write!(writer, "point")?;
+        for key in &self.keys {
+            write!(writer, ",{}", csv_field(key))?;
+        }
+        write!(writer, ",runs,extinct,population_mean,population_stddev")?;
+        for name in &traits {
+            write!(
+                writer,
+                ",{},{}",
+                csv_field(&format!("{}_mean", name)),
+                csv_field(&format!("{}_stddev", name))
+            )?;
+        }
+        writeln!(writer)?;
+        let mut points: BTreeMap<usize, Vec<(&BatchRun, &RunSummary)>> = BTreeMap::new();
+        for (run, summary) in self.runs.iter().zip(summaries) {
+            points.entry(run.point).or_default().push((run, summary));
+        }
+        for (point, runs) in points {
+            write!(writer, "{}", point)?;
+            for key in &self.keys {
+                write!(writer, ",{}", parameter(&runs[0].0.parameters, key))?;
+            }
+            let population = TraitStats::new(
+                "population",
+                runs.iter().map(|(_, s)| s.population() as f32),
+            );
+            write!(
+                writer,
+                ",{},{},{},{}",
+                runs.len(),
+                runs.iter().filter(|(_, s)| s.extinct.is_some()).count(),
+                population.mean,
+                population.stddev
+            )?;
+            for name in &traits {
+                // Extinct runs have no traits left to average
+                let means = TraitStats::new(
+                    name,
+                    runs.iter()
+                        .map(|(_, s)| s.trait_mean(name))
+                        .filter(|m| !m.is_nan()),
+                );
+                write!(
+                    writer,
+                    ",{},{}",
+                    csv_number(means.mean),
+                    csv_number(means.stddev)
+                )?;
+            }
+            writeln!(writer)?;
+        }
+        writer.flush()
+    }
+}
+
+// ============================================================================
+// Headless Run
+// ============================================================================
+
+/// Final state of a headless run
+#[derive(Debug, Clone, PartialEq)]
+pub struct RunSummary {
+    pub generations: u32,
+    /// Generation after which no blob was left
+    pub extinct: Option<u32>,
+    pub last: Option<GenerationStats>,
+}
+
+impl RunSummary {
+    pub fn population(&self) -> usize {
+        self.last.as_ref().map_or(0, |stats| stats.population)
+    }
+
+    pub fn trait_mean(&self, name: &str) -> f32 {
+        self.last
+            .as_ref()
+            .and_then(|stats| stats.trait_stats(name))
+            .map_or(f32::NAN, |t| t.mean)
+    }
+}
+
+/// Runs `core` for `generations` generations
+///
+/// If `output` is given the generation statistics are written to
+/// `output.csv` or `output.jsonl`, the effective settings, including the seed,
+/// to `output.toml`, the final state to `output.snapshot` and the lineage of
+/// all blobs to `output.nwk` and `output.lineage.csv`.
+pub fn run_headless<F: FnMut(&GenerationStats)>(
+    core: &mut SimulationCore,
+    generations: u32,
+    output: Option<&Path>,
+    format: StatsFormat,
+    mut on_generation: F,
+) -> Result<RunSummary, BatchError> {
+    let mut recorder = match output {
+        Some(output) => {
+            core.settings().save(output.with_extension("toml"))?;
+            Some(StatsRecorder::create(
+                output.with_extension(format.extension()),
+                format,
+            )?)
+        }
+        None => None,
+    };
+    let mut summary = RunSummary {
+        generations,
+        extinct: None,
+        last: None,
+    };
+    for _ in 0..generations {
+        let stats = core.run_generation();
+        on_generation(&stats);
+        if let Some(recorder) = &mut recorder {
+            recorder.record(&stats)?;
+        }
+        if stats.population == 0 && summary.extinct.is_none() {
+            summary.extinct = Some(stats.generation);
+        }
+        summary.last = Some(stats);
+    }
+    if let Some(recorder) = &mut recorder {
+        recorder.flush()?;
+    }
+    if let Some(output) = output {
+        snapshot::save(core, output.with_extension("snapshot"))?;
+        core.lineage().save(output)?;
+    }
+    Ok(summary)
+}
+
+// ============================================================================
+// Helpers
+// ============================================================================
+
+fn trait_names(summaries: &[RunSummary]) -> Vec<String> {
+    summaries
+        .iter()
+        .find_map(|summary| summary.last.as_ref())
+        .map(|stats| stats.traits.iter().map(|t| t.name.clone()).collect())
+        .unwrap_or_default()
+}
+
+fn parameter(parameters: &Parameters, key: &str) -> String {
+    parameters
+        .get(key)
+        .map(|value| csv_field(&value.to_string()))
+        .unwrap_or_default()
+}
+
+// ============================================================================
+// Batch Error
+// ============================================================================
+
+#[derive(Debug)]
+pub enum BatchError {
+    Io(io::Error),
+    Settings(SettingsError),
+    Snapshot(SnapshotError),
+}
+
+impl fmt::Display for BatchError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            BatchError::Io(e) => write!(f, "batch io: {}", e),
+            BatchError::Settings(e) => write!(f, "batch {}", e),
+            BatchError::Snapshot(e) => write!(f, "batch {}", e),
+        }
+    }
+}
+
+impl error::Error for BatchError {}
+
+impl From<io::Error> for BatchError {
+    fn from(e: io::Error) -> BatchError {
+        BatchError::Io(e)
+    }
+}
+
+impl From<SettingsError> for BatchError {
+    fn from(e: SettingsError) -> BatchError {
+        BatchError::Settings(e)
+    }
+}
+
+impl From<SnapshotError> for BatchError {
+    fn from(e: SnapshotError) -> BatchError {
+        BatchError::Snapshot(e)
+    }
+}
+
+// ============================================================================
+// Testing
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse_sweep(text: &str) -> Sweep {
+        toml::from_str(text).unwrap()
+    }
+
+    fn seeds(batch: &Batch) -> Vec<u64> {
+        batch
+            .runs()
+            .iter()
+            .map(|run| run.settings.seed().unwrap())
+            .collect()
+    }
+
+    #[test]
+    fn empty_sweep_is_a_single_combination() {
+        let sweep = Sweep::default();
+        assert_eq!(sweep.combinations(), vec![Parameters::new()]);
+        assert!(sweep.keys().is_empty());
+    }
+
+    #[test]
+    fn points_are_combined_with_the_grid() {
+        let grid = parse_sweep(
+            r#"
+            [grid]
+            start_blobs = [8, 16]
+            "genes.speed.mutation" = [0.25, 0.5]
+            "#,
+        );
+        assert_eq!(grid.combinations().len(), 4);
+        let sweep = parse_sweep(
+            r#"
+            [grid]
+            start_blobs = [8, 16]
+            "genes.speed.mutation" = [0.25, 0.5]
+
+            [[points]]
+            tick_rate = 30
+
+            [[points]]
+            tick_rate = 60
+            fps = 30
+            "#,
+        );
+        let combinations = sweep.combinations();
+        assert_eq!(combinations.len(), 8);
+        // Every point with every combination of the grid, points first
+        for (i, combination) in combinations.iter().enumerate() {
+            let tick_rate = if i < 4 { 30 } else { 60 };
+            assert_eq!(combination["tick_rate"].as_integer(), Some(tick_rate));
+            assert_eq!(combination.contains_key("fps"), i >= 4);
+            assert_eq!(
+                combination["start_blobs"].as_integer(),
+                Some([8, 16][i % 2])
+            );
+        }
+        assert_eq!(
+            combinations
+                .iter()
+                .map(|c| c["genes.speed.mutation"].as_float().unwrap())
+                .collect::<Vec<f64>>(),
+            [0.25, 0.25, 0.5, 0.5, 0.25, 0.25, 0.5, 0.5]
+        );
+        assert_eq!(
+            sweep.keys(),
+            ["fps", "genes.speed.mutation", "start_blobs", "tick_rate"]
+        );
+    }
+
+    #[test]
+    fn replicates_are_seeded_in_sequence() {
+        let mut base = Settings::default();
+        base.set_seed(Some(10));
+        let sweep = parse_sweep(
+            r#"
+            [[points]]
+            start_blobs = 4
+
+            [[points]]
+            seed = 100
+            "#,
+        );
+        let batch = Batch::new(&base, &sweep, 3).unwrap();
+        assert_eq!(seeds(&batch), [10, 11, 12, 100, 101, 102]);
+        assert_eq!(
+            batch.runs().iter().map(|r| r.point).collect::<Vec<usize>>(),
+            [0, 0, 0, 1, 1, 1]
+        );
+        // Seeds wrap around within what TOML can save
+        base.set_seed(Some(simulation::MAX_SEED));
+        let batch = Batch::new(&base, &Sweep::default(), 2).unwrap();
+        assert_eq!(seeds(&batch), [simulation::MAX_SEED, 0]);
+        // Without a seed all points share a random one
+        base.set_seed(None);
+        let batch =
+            Batch::new(&base, &parse_sweep(r#"grid = { start_blobs = [4, 8] }"#), 2).unwrap();
+        let seeds = seeds(&batch);
+        assert_eq!(seeds[..2], seeds[2..]);
+        assert_eq!(seeds[1], seeds[0] + 1);
+    }
+}
@@ -0,0 +1,84 @@
+//! blob-sim blob index
+//!
+
+// ============================================================================
+// Imports
+// ============================================================================
+
+use super::super::grid::SpatialGrid;
+use super::{Blob, BlobState};
+use ggez::nalgebra::Point2;
+
+// ============================================================================
+// Blob Index
+// ============================================================================
+
+/// Where the blobs in the field were at the start of a tick, bucketed into
+/// square cells of a [`SpatialGrid`] like the food
+///
+/// Blobs at home are left out, they can neither hunt nor be hunted. Blobs on
+/// their way home are still in, they no longer hunt but can be eaten until
+/// they arrive, prey only flees from blobs still
+/// [`hunting`](BlobEntry::hunting).
+pub struct BlobIndex {
+    grid: SpatialGrid<usize>,
+    /// Ordered by id
+    entries: Vec<BlobEntry>,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct BlobEntry {
+    pub id: usize,
+    pub species: usize,
+    pub position: Point2<f32>,
+    pub size: f32,
+    /// Out in the field and still able to hunt
+    pub hunting: bool,
+    /// Looking for a mate
+    pub seeking: bool,
+}
+
+impl BlobIndex {
+    /// Indexes `blobs`, which have to be ordered by id
+    pub fn new(blobs: &[Blob], world_size: (f32, f32), cell_size: f32) -> BlobIndex {
+        let mut index = BlobIndex {
+            grid: SpatialGrid::new(world_size, cell_size),
+            entries: vec![],
+        };
+        for blob in blobs.iter().filter(|b| b.state() != BlobState::AtHome) {
+            index.grid.insert(index.entries.len(), blob.position());
+            index.entries.push(BlobEntry {
+                id: blob.id(),
+                species: blob.species(),
+                position: blob.position(),
+                size: blob.size(),
+                hunting: blob.in_field(),
+                seeking: blob.state() == BlobState::SeekMate,
+            });
+        }
+        index
+    }
+
+    /// Blob with `id`, if it was in the field
+    pub fn get(&self, id: usize) -> Option<&BlobEntry> {
+        self.entries
+            .binary_search_by_key(&id, |entry| entry.id)
+            .ok()
+            .map(|i| &self.entries[i])
+    }
+
+    /// Nearest blob within `radius` of `position` matching `filter`
+    ///
+    /// Blobs at equal distance are ordered by id.
+    pub fn nearest<F: Fn(&BlobEntry) -> bool>(
+        &self,
+        position: Point2<f32>,
+        radius: f32,
+        filter: F,
+    ) -> Option<&BlobEntry> {
+        // Entries are ordered by id, so are their indices
+        self.grid
+            .nearest(position, radius, |i| filter(&self.entries[i]))
+            .map(|(i, _)| &self.entries[i])
+    }
+}
@@ -5,134 +5,188 @@
 // Modules
 // ============================================================================
 
-// ============================================================================
-// Imports
-// ============================================================================
-
-use ggez::nalgebra::{distance, Point2, Translation2};
-use rand::random;
-use std::sync::{
-    atomic::{AtomicUsize, Ordering},
-    Arc, RwLock,
-};
+pub mod index;
 
 // ============================================================================
-// Statics
+// Imports
 // ============================================================================
 
-static ID_CNT: AtomicUsize = AtomicUsize::new(0);
+use super::food::{FoodGrid, FoodId};
+use super::genome::{self, Genome};
+use super::settings::{Reproduction, Settings};
+use ggez::nalgebra::{distance, Point2, Translation2, Vector2};
+use index::BlobIndex;
+use rand::Rng;
+use serde::{Deserialize, Serialize};
 
 // ============================================================================
 // The Blob
 // ============================================================================
 
-#[derive(Clone)]
+#[derive(Clone, Serialize, Deserialize)]
 pub struct Blob {
     id: usize,
+    /// Index of the species in the settings
+    species: usize,
+    // Lineage
+    parents: Vec<usize>,
+    born: u32,
     // Base Stats
     energy: f32,
-    speed: f32,
-    sense: f32,
-    size: f32,
+    genome: Genome,
     // State
     state: BlobState,
+    mate: Option<Mate>,
     // Generation Stats
     food_found: u8,
     // Map
     destination: Point2<f32>,
     position: Point2<f32>,
-    // Settings
-    settings: Arc<RwLock<super::Settings>>,
+    /// Position before the last tick, for drawing in between ticks
+    last_position: Point2<f32>,
+    food_target: Option<FoodId>,
+    prey_target: Option<usize>,
 }
 
 impl Blob {
-    pub fn new(settings: Arc<RwLock<super::Settings>>) -> Blob {
+    /// Founder of `species`, `settings` being the settings of the species
+    pub fn new<R: Rng + ?Sized>(
+        id: usize,
+        species: usize,
+        settings: &Settings,
+        rng: &mut R,
+    ) -> Blob {
+        let (destination, position) = Blob::spawn(settings, rng);
         Blob {
-            id: ID_CNT.fetch_add(1, Ordering::AcqRel),
+            id,
+            species,
+            // Lineage
+            parents: vec![],
+            born: 0,
             // Base Stats
-            energy: settings.read().unwrap().blob_energy(),
-            speed: settings.read().unwrap().blob_speed().0,
-            sense: settings.read().unwrap().blob_sense().0,
-            size: settings.read().unwrap().blob_size().0,
+            energy: settings.blob_energy(),
+            genome: Genome::new(settings.genes()),
             // State
             state: BlobState::SearchFood,
+            mate: None,
             // Generation Stats
             food_found: 0,
             // Map
-            destination: Point2::new(
-                random::<f32>() * settings.read().unwrap().world_size().0,
-                random::<f32>() * settings.read().unwrap().world_size().1,
-            ),
-            position: Point2::new(
-                random::<f32>() * settings.read().unwrap().world_size().0,
-                random::<f32>() * settings.read().unwrap().world_size().1,
-            ),
-            // Settings
-            settings: settings.clone(),
+            destination,
+            position,
+            last_position: position,
+            food_target: None,
+            prey_target: None,
         }
     }
 
-    pub fn evolve(from_blob: &Blob) -> Blob {
-        let modifier = random::<usize>() % 3;
-        let size = from_blob.size()
-            * if modifier == 2 {
-                1.0 + (random::<f32>() * from_blob.settings.read().unwrap().blob_size().1)
-                    - from_blob.settings.read().unwrap().blob_size().1 / 2.0
-            } else {
-                1.0
-            };
+    /// Offspring of `from_blob` and its `mate`, if any, born into generation
+    /// `born`
+    ///
+    /// With a mate every gene is a crossover of both parents first. Then the
+    /// genome is mutated by the mutation model of the settings. Offspring
+    /// belong to the species of `from_blob`, whose settings `settings` are.
+    pub fn evolve<R: Rng>(
+        id: usize,
+        born: u32,
+        from_blob: &Blob,
+        mate: Option<&Mate>,
+        settings: &Settings,
+        rng: &mut R,
+    ) -> Blob {
+        let mut genome = match mate {
+            Some(mate) => from_blob
+                .genome()
+                .cross(&mate.genome, settings.crossover(), rng),
+            None => from_blob.genome().clone(),
+        };
+        settings
+            .mutation()
+            .model()
+            .mutate(&mut genome, settings.genes(), rng);
+        let (destination, position) = Blob::spawn(settings, rng);
         Blob {
-            id: ID_CNT.fetch_add(1, Ordering::AcqRel),
+            id,
+            species: from_blob.species(),
+            // Lineage
+            parents: Some(from_blob.id())
+                .into_iter()
+                .chain(mate.map(|mate| mate.id))
+                .collect(),
+            born,
             // Base Stats
-            energy: from_blob.settings.read().unwrap().blob_energy() * size,
-            speed: from_blob.speed()
-                * if modifier == 0 {
-                    1.0 + (random::<f32>() * from_blob.settings.read().unwrap().blob_speed().1)
-                        - from_blob.settings.read().unwrap().blob_speed().1 / 2.0
-                } else {
-                    1.0
-                },
-            sense: from_blob.sense()
-                * if modifier == 1 {
-                    1.0 + (random::<f32>() * from_blob.settings.read().unwrap().blob_sense().1)
-                        - from_blob.settings.read().unwrap().blob_sense().1 / 2.0
-                } else {
-                    1.0
-                },
-            size,
+            energy: settings.blob_energy() * genome[genome::SIZE],
+            genome,
             // State
             state: BlobState::SearchFood,
+            mate: None,
             // Generation Stats
             food_found: 0,
             // Map
-            destination: Point2::new(
-                random::<f32>() * from_blob.settings.read().unwrap().world_size().0,
-                random::<f32>() * from_blob.settings.read().unwrap().world_size().1,
-            ),
-            position: Point2::new(
-                random::<f32>() * from_blob.settings.read().unwrap().world_size().0,
-                random::<f32>() * from_blob.settings.read().unwrap().world_size().1,
-            ),
-            // Settings
-            settings: from_blob.settings.clone(),
+            destination,
+            position,
+            last_position: position,
+            food_target: None,
+            prey_target: None,
         }
     }
+    /// Random destination and position in the world
+    fn spawn<R: Rng + ?Sized>(settings: &Settings, rng: &mut R) -> (Point2<f32>, Point2<f32>) {
+        let destination = Point2::new(
+            rng.gen::<f32>() * settings.world_size().0,
+            rng.gen::<f32>() * settings.world_size().1,
+        );
+        let position = Point2::new(
+            rng.gen::<f32>() * settings.world_size().0,
+            rng.gen::<f32>() * settings.world_size().1,
+        );
+        (destination, position)
+    }
+
+    #[inline(always)]
+    pub fn id(&self) -> usize {
+        self.id
+    }
+    /// Index of the species in the settings, `0` without species
+    #[inline(always)]
+    pub fn species(&self) -> usize {
+        self.species
+    }
+
+    // Lineage
+    /// Ids of the blobs this one was born from, none for the first
+    /// generation, two with sexual reproduction
+    #[inline(always)]
+    pub fn parents(&self) -> &[usize] {
+        &self.parents
+    }
+    /// Generation the blob was born into
+    #[inline(always)]
+    pub fn born(&self) -> u32 {
+        self.born
+    }
+
     // Stats
     #[inline(always)]
     pub fn energy(&self) -> f32 {
         self.energy
     }
+    /// Values of all genes, in the order of the settings' genes
+    #[inline(always)]
+    pub fn genome(&self) -> &Genome {
+        &self.genome
+    }
     #[inline(always)]
     pub fn speed(&self) -> f32 {
-        self.speed
+        self.genome[genome::SPEED]
     }
     #[inline(always)]
     pub fn sense(&self) -> f32 {
-        self.sense
+        self.genome[genome::SENSE]
     }
     #[inline(always)]
     pub fn size(&self) -> f32 {
-        self.size
+        self.genome[genome::SIZE]
     }
 
     // State
@@ -140,42 +194,155 @@ impl Blob {
     pub fn state(&self) -> BlobState {
         self.state
     }
+    /// Food eaten in the current generation
+    #[inline(always)]
+    pub fn food_found(&self) -> u8 {
+        self.food_found
+    }
+    /// Mate met in the current generation
+    #[inline(always)]
+    pub fn mate(&self) -> Option<&Mate> {
+        self.mate.as_ref()
+    }
 
     // Actions
-    pub fn update(&mut self, food: &mut Vec<Point2<f32>>) {
+    /// First phase of a tick, moves the blob
+    ///
+    /// Food and other blobs are only looked at, a blob reaching its food or
+    /// prey returns a claim for it instead of eating, the same goes for
+    /// meeting a mate. The simulation resolves all claims of a tick and calls
+    /// [`Blob::eat`], [`Blob::eat_prey`], [`Blob::missed_food`] or
+    /// [`Blob::pair`], followed by [`Blob::check_home`] for every blob.
+    ///
+    /// `blobs` is only given with predation or sexual reproduction enabled.
+    /// `settings` are those of the blob's species, mates are only found
+    /// within the species while predators and prey may be of any.
+    pub fn update<R: Rng + ?Sized>(
+        &mut self,
+        food: &FoodGrid,
+        blobs: Option<&BlobIndex>,
+        settings: &Settings,
+        rng: &mut R,
+    ) -> Option<Claim> {
+        self.last_position = self.position;
+        if let (Some(blobs), true) = (blobs.filter(|_| settings.predation()), self.in_field()) {
+            // Running away comes first
+            let threshold = self.size() * settings.predation_ratio();
+            if let Some(predator) = blobs.nearest(self.position(), self.sense(), |b| {
+                b.id != self.id && b.hunting && b.size >= threshold
+            }) {
+                self.flee(predator.position, settings);
+                self.move_to(settings);
+                return None;
+            }
+        }
         match self.state() {
             BlobState::SearchFood => {
-                if let Some(next_food) = self.search_food(food) {
+                let next_food = food.nearest(self.position(), self.sense());
+                let prey = blobs.filter(|_| settings.predation()).and_then(|blobs| {
+                    let threshold = self.size() / settings.predation_ratio();
+                    blobs.nearest(self.position(), self.sense(), |b| {
+                        b.id != self.id && b.size <= threshold
+                    })
+                });
+                let food_distance = next_food.map(|(_, f)| distance(&self.position(), &f));
+                if let Some(prey) = prey.filter(|p| {
+                    food_distance.is_none_or(|d| distance(&self.position(), &p.position) < d)
+                }) {
+                    // Set prey as destination and chase it
+                    self.destination = prey.position;
+                    self.prey_target = Some(prey.id);
+                    self.state = BlobState::Hunt;
+                } else if let Some((id, next_food)) = next_food {
                     // Set food as destination and go to
                     self.destination = next_food;
+                    self.food_target = Some(id);
                     self.state = BlobState::GoToFood;
-                } else if self.position() == self.destination() {
-                    // Generate random destination
-                    self.destination = Point2::new(
-                        random::<f32>() * self.settings.read().unwrap().world_size().0,
-                        random::<f32>() * self.settings.read().unwrap().world_size().1,
-                    );
+                } else {
+                    self.wander(settings, rng);
                 }
-                self.move_to();
+                self.move_to(settings);
             }
             BlobState::GoToFood => {
-                if food.contains(&self.destination()) {
-                    if self.move_to() {
-                        // Eat and remove food from list
-                        self.eat();
-                        food.retain(|&f| f != self.destination());
-                        // Search for more
-                        self.state = BlobState::SearchFood;
+                match self.food_target.filter(|&id| food.contains(id)) {
+                    Some(id) => {
+                        // Fraction of this tick's step needed to reach the food
+                        let arrival = distance(&self.position(), &self.destination())
+                            / (settings.blob_step() * self.speed());
+                        if self.move_to(settings) {
+                            // Claim food, eaten once the claim is resolved
+                            return Some(Claim {
+                                target: Target::Food(id),
+                                arrival,
+                            });
+                        }
+                    }
+                    None => {
+                        // Sombody else ate it, go back searching
+                        self.missed_food();
+                        return self.update(food, blobs, settings, rng);
+                    }
+                }
+            }
+            BlobState::Hunt => {
+                let prey = self
+                    .prey_target
+                    .and_then(|id| blobs.and_then(|blobs| blobs.get(id)));
+                match prey {
+                    Some(prey) => {
+                        // Follow the prey to where it is now
+                        self.destination = prey.position;
+                        let arrival = distance(&self.position(), &self.destination())
+                            / (settings.blob_step() * self.speed());
+                        if self.move_to(settings) {
+                            // Claim prey, eaten once the claim is resolved
+                            return Some(Claim {
+                                target: Target::Prey(prey.id),
+                                arrival,
+                            });
+                        }
+                    }
+                    None => {
+                        // Eaten by somebody else or safe at home
+                        self.missed_food();
+                        return self.update(food, blobs, settings, rng);
+                    }
+                }
+            }
+            BlobState::Flee => {
+                // Nothing to run from anymore
+                self.missed_food();
+                return self.update(food, blobs, settings, rng);
+            }
+            BlobState::SeekMate => {
+                let mate = blobs.and_then(|blobs| {
+                    blobs.nearest(self.position(), self.sense(), |b| {
+                        b.id != self.id && b.seeking && b.species == self.species
+                    })
+                });
+                match mate {
+                    Some(mate) => {
+                        // Meet the mate where it is now
+                        self.destination = mate.position;
+                        let arrival = distance(&self.position(), &self.destination())
+                            / (settings.blob_step() * self.speed());
+                        if self.move_to(settings) {
+                            // Claim mate, paired once the claim is resolved
+                            return Some(Claim {
+                                target: Target::Mate(mate.id),
+                                arrival,
+                            });
+                        }
+                    }
+                    None => {
+                        self.wander(settings, rng);
+                        self.move_to(settings);
                     }
-                } else {
-                    // Sombody else ate it, go back searching
-                    self.state = BlobState::SearchFood;
-                    self.update(food);
                 }
             }
             BlobState::GoHome => {
                 // Just move until at home
-                if self.move_to() {
+                if self.move_to(settings) {
                     self.state = BlobState::AtHome;
                 }
             }
@@ -183,38 +350,42 @@ impl Blob {
                 // Do nothing, wait for next gen
             }
         }
-        // Go home on low energy and when enough food found
+        None
+    }
+
+    /// Goes home on low energy and once the selection rule is satisfied
+    ///
+    /// With sexual reproduction satisfied blobs look for a mate first, as
+    /// long as they have energy to spare for the way home.
+    pub fn check_home(&mut self, settings: &Settings) {
         match self.state() {
-            BlobState::SearchFood | BlobState::GoToFood => {
-                if (self.energy() < self.settings.read().unwrap().food_energy() * 3.0
-                    && self.food_found == 1)
-                    || self.food_found >= 2
+            BlobState::SearchFood
+            | BlobState::GoToFood
+            | BlobState::Hunt
+            | BlobState::Flee
+            | BlobState::SeekMate => {
+                let home = self.home(settings);
+                let satisfied = settings.selection().rule().satisfied(self, settings);
+                if satisfied
+                    && settings.reproduction() == Reproduction::Sexual
+                    && self.mate.is_none()
+                    && self.energy()
+                        > 2.0 * self.move_cost(distance(&self.position(), &home), settings)
                 {
-                    // Get distance to next edge on x axis
-                    let distance_x = if self.position()[0]
-                        > (self.settings.read().unwrap().world_size().0 / 2.0)
-                    {
-                        self.position()[0] - self.settings.read().unwrap().world_size().0
-                    } else {
-                        self.position()[0]
-                    };
-                    // Get distance to nect edge on y axis
-                    let distance_y = if self.position()[1]
-                        > (self.settings.read().unwrap().world_size().1 / 2.0)
-                    {
-                        self.position()[1] - self.settings.read().unwrap().world_size().1
-                    } else {
-                        self.position()[1]
-                    };
-                    // Set destination to next edge
-                    if distance_x.abs() < distance_y.abs() {
-                        self.destination =
-                            Point2::new(self.position()[0] - distance_x, self.position()[1]);
-                    } else {
-                        self.destination =
-                            Point2::new(self.position()[0], self.position()[1] - distance_y);
+                    // Find a mate first
+                    if self.state() != BlobState::SeekMate {
+                        self.food_target = None;
+                        self.prey_target = None;
+                        self.state = BlobState::SeekMate;
                     }
+                } else if (self.energy() < settings.food_energy() * 3.0 && self.food_found >= 1)
+                    || satisfied
+                {
+                    // Set destination to next edge
+                    self.destination = home;
                     // Go home
+                    self.food_target = None;
+                    self.prey_target = None;
                     self.state = BlobState::GoHome;
                 }
             }
@@ -224,39 +395,119 @@ impl Blob {
         }
     }
 
-    fn search_food(&mut self, food: &mut Vec<Point2<f32>>) -> Option<Point2<f32>> {
-        food.iter()
-            // Filter food for stuff in sense range
-            .filter(|x| distance(&self.position(), &x).abs() <= self.sense())
-            // Return nearest food
-            .min_by(|x, y| {
-                distance(&self.position(), &x)
-                    .abs()
-                    .partial_cmp(&distance(&self.position(), &y).abs())
-                    .unwrap()
-            })
-            .copied()
+    /// Eats the claimed food and searches for more
+    pub fn eat(&mut self, settings: &Settings) {
+        // Get energy from food
+        self.energy += settings.food_energy() * self.size();
+        // Add collected food
+        self.food_found = self.food_found.saturating_add(1);
+        // Search for more
+        self.food_target = None;
+        self.state = BlobState::SearchFood;
     }
 
-    fn eat(&mut self) {
-        // Get energy from food
-        self.energy = self.energy() + self.settings.read().unwrap().food_energy() * self.size();
+    /// Eats the claimed prey, which counts as food, and searches for more
+    pub fn eat_prey(&mut self, prey: &Blob) {
+        // Get the energy the prey had left
+        self.energy += prey.energy();
         // Add collected food
-        self.food_found = self.food_found + 1;
+        self.food_found = self.food_found.saturating_add(1);
+        // Search for more
+        self.prey_target = None;
+        self.state = BlobState::SearchFood;
+    }
+
+    /// Pairs up with `mate` for this generation
+    pub fn pair(&mut self, mate: &Blob) {
+        self.mate = Some(Mate {
+            id: mate.id(),
+            genome: mate.genome().clone(),
+        });
+        // On the way home with the next check
+        self.state = BlobState::SearchFood;
+    }
+
+    /// Gives up the current food or prey and searches for other food
+    pub fn missed_food(&mut self) {
+        self.food_target = None;
+        self.prey_target = None;
+        self.state = BlobState::SearchFood;
+    }
+
+    /// Whether the blob is out in the field and able to hunt
+    pub fn in_field(&self) -> bool {
+        match self.state() {
+            BlobState::SearchFood
+            | BlobState::GoToFood
+            | BlobState::Hunt
+            | BlobState::Flee
+            | BlobState::SeekMate => true,
+            BlobState::GoHome | BlobState::AtHome => false,
+        }
+    }
+
+    /// Nearest point on the edge of the world
+    fn home(&self, settings: &Settings) -> Point2<f32> {
+        // Get distance to next edge on x axis
+        let distance_x = if self.position()[0] > (settings.world_size().0 / 2.0) {
+            self.position()[0] - settings.world_size().0
+        } else {
+            self.position()[0]
+        };
+        // Get distance to nect edge on y axis
+        let distance_y = if self.position()[1] > (settings.world_size().1 / 2.0) {
+            self.position()[1] - settings.world_size().1
+        } else {
+            self.position()[1]
+        };
+        if distance_x.abs() < distance_y.abs() {
+            Point2::new(self.position()[0] - distance_x, self.position()[1])
+        } else {
+            Point2::new(self.position()[0], self.position()[1] - distance_y)
+        }
     }
 
-    fn move_to(&mut self) -> bool {
+    /// Heads somewhere else at random once the destination is reached
+    fn wander<R: Rng + ?Sized>(&mut self, settings: &Settings, rng: &mut R) {
+        if self.position() == self.destination() {
+            // Generate random destination
+            self.destination = Point2::new(
+                rng.gen::<f32>() * settings.world_size().0,
+                rng.gen::<f32>() * settings.world_size().1,
+            );
+        }
+    }
+
+    /// Runs straight away from a predator at `predator`
+    fn flee(&mut self, predator: Point2<f32>, settings: &Settings) {
+        let away = self.position() - predator;
+        let direction = if away.norm() > 0.0 {
+            away.normalize()
+        } else {
+            Vector2::x()
+        };
+        let target = self.position() + direction * self.sense();
+        self.destination = Point2::new(
+            target.x.clamp(0.0, settings.world_size().0),
+            target.y.clamp(0.0, settings.world_size().1),
+        );
+        self.food_target = None;
+        self.prey_target = None;
+        self.state = BlobState::Flee;
+    }
+
+    fn move_to(&mut self, settings: &Settings) -> bool {
         // Max move pixel times speed
-        let max_distance = self.settings.read().unwrap().blob_step() * self.speed();
+        let max_distance = settings.blob_step() * self.speed();
         // Distance to target
         let distance_to_target = distance(&self.position(), &self.destination());
         // Move either to target if smaller than max possible distance or max_distance
         let distance_to_move = distance_to_target.abs().min(max_distance);
         // Calculate energy needed for move
-        let needed_energy = distance_to_move * self.speed().powi(2) * self.size().powi(3);
+        let needed_energy = self.move_cost(distance_to_move, settings);
         // Update position if enough energy and return if target reached
         if self.energy() >= needed_energy {
-            self.energy = self.energy() - needed_energy;
+            self.energy -= needed_energy;
             if distance_to_target == distance_to_move {
                 // New position equals target
                 self.position = self.destination();
@@ -275,29 +526,33 @@ impl Blob {
         }
     }
 
-    pub fn next_gen(&mut self) -> GenerationResult {
+    /// Energy needed to move `distance`
+    fn move_cost(&self, distance: f32, settings: &Settings) -> f32 {
+        self.genome.move_cost(settings.genes(), distance)
+    }
+
+    /// Ends the generation for the blob, judged by the selection rule
+    pub fn next_gen<R: Rng>(&mut self, settings: &Settings, rng: &mut R) -> GenerationResult {
         // Check round outcome...
-        let result = if self.state() == BlobState::AtHome {
-            match self.food_found {
-                0 => GenerationResult::Starve,
-                1 => GenerationResult::Live,
-                2 => GenerationResult::Reproduce,
-                _ => unreachable!(),
-            }
+        let result = if self.state() == BlobState::AtHome || !settings.return_home() {
+            settings.selection().rule().select(self, settings, rng)
         } else {
             GenerationResult::Starve
         };
         // ...reset self...
-        self.energy = self.settings.read().unwrap().blob_energy() * self.size();
+        self.energy = settings.blob_energy() * self.size();
         self.state = BlobState::SearchFood;
         self.food_found = 0;
+        self.food_target = None;
+        self.prey_target = None;
+        self.mate = None;
         /*self.destination = Point2::new(
-            random::<f32>() * self.settings.read().unwrap().world_size().0,
-            random::<f32>() * self.settings.read().unwrap().world_size().1,
+            rng.gen::<f32>() * settings.world_size().0,
+            rng.gen::<f32>() * settings.world_size().1,
         );
         self.position = Point2::new(
-            random::<f32>() * self.settings.read().unwrap().world_size().0,
-            random::<f32>() * self.settings.read().unwrap().world_size().1,
+            rng.gen::<f32>() * settings.world_size().0,
+            rng.gen::<f32>() * settings.world_size().1,
         );*/
         // ...and return outcome
         result
@@ -312,6 +567,20 @@ impl Blob {
     pub fn position(&self) -> Point2<f32> {
         self.position
     }
+    /// Position `alpha` of the way from the previous to the current tick
+    pub fn position_at(&self, alpha: f32) -> Point2<f32> {
+        self.last_position + (self.position - self.last_position) * alpha
+    }
+    /// Food the blob is currently heading for
+    #[inline(always)]
+    pub fn food_target(&self) -> Option<FoodId> {
+        self.food_target
+    }
+    /// Id of the blob currently being hunted
+    #[inline(always)]
+    pub fn prey_target(&self) -> Option<usize> {
+        self.prey_target
+    }
 }
 
 impl PartialEq for Blob {
@@ -324,22 +593,61 @@ impl PartialEq for Blob {
 // Blob State
 // ============================================================================
 
-#[derive(Debug, Copy, Clone, PartialEq)]
+#[derive(Debug, Copy, Clone, PartialEq, Serialize, Deserialize)]
 pub enum BlobState {
     SearchFood,
     GoToFood,
+    Hunt,
+    Flee,
+    SeekMate,
     GoHome,
     AtHome,
 }
 
+// ============================================================================
+// Claim
+// ============================================================================
+
+/// Food or prey reached by a blob during a tick
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Claim {
+    pub target: Target,
+    /// Fraction of the tick the blob needed to get there, first come first
+    /// served
+    pub arrival: f32,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum Target {
+    Food(FoodId),
+    /// Id of the hunted blob
+    Prey(usize),
+    /// Id of the blob to pair up with
+    Mate(usize),
+}
+
+// ============================================================================
+// Mate
+// ============================================================================
+
+/// Genome of the blob paired up with, kept in case it dies before its mate
+/// reproduces
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Mate {
+    pub id: usize,
+    pub genome: Genome,
+}
+
 // ============================================================================
 // Generation Result
 // ============================================================================
 
+#[derive(Debug, Copy, Clone, PartialEq)]
 pub enum GenerationResult {
     Starve,
     Live,
-    Reproduce,
+    /// Survives with this many offspring
+    Reproduce(u32),
 }
 
 // ============================================================================
@@ -0,0 +1,304 @@
+//! blob-sim command line interface
+//!
+
+// ============================================================================
+// Imports
+// ============================================================================
+
+use super::genome::mutation::Mutation;
+use super::settings::{Crossover, Reproduction, Settings, SettingsError};
+use super::simulation::{SimulationCore, MAX_SEED};
+use super::snapshot;
+use super::stats::StatsFormat;
+use std::{error::Error, fmt::Display, path::PathBuf, str::FromStr};
+use structopt::StructOpt;
+
+// ============================================================================
+// Command Line
+// ============================================================================
+
+#[derive(Debug, StructOpt)]
+#[structopt(about = "Blob Life Simulation")]
+pub struct Cli {
+    #[structopt(subcommand)]
+    command: Option<Command>,
+}
+
+impl Cli {
+    /// Selected subcommand, running the window with defaults if none given
+    pub fn command(self) -> Command {
+        self.command.unwrap_or(Command::Run {
+            settings: SettingsArgs::default(),
+            resume: ResumeArgs::default(),
+            snapshot: PathBuf::from("blob_sim.snapshot"),
+        })
+    }
+}
+
+#[derive(Debug, StructOpt)]
+pub enum Command {
+    /// Runs the simulation in a window
+    Run {
+        #[structopt(flatten)]
+        settings: SettingsArgs,
+        #[structopt(flatten)]
+        resume: ResumeArgs,
+        /// Snapshot file written with F5 and read with F9
+        #[structopt(long, parse(from_os_str), default_value = "blob_sim.snapshot")]
+        snapshot: PathBuf,
+    },
+    /// Runs the simulation without a window
+    Headless {
+        #[structopt(flatten)]
+        settings: SettingsArgs,
+        #[structopt(flatten)]
+        output: OutputArgs,
+        #[structopt(flatten)]
+        resume: ResumeArgs,
+    },
+    /// Runs headless simulations in parallel, optionally sweeping parameters
+    Batch {
+        #[structopt(flatten)]
+        settings: SettingsArgs,
+        #[structopt(flatten)]
+        output: OutputArgs,
+        /// Runs per parameter combination, seeded with the seed plus the
+        /// replicate index
+        #[structopt(long, default_value = "4")]
+        runs: u32,
+        /// TOML file with the parameter grid and points to sweep
+        #[structopt(long, parse(from_os_str))]
+        sweep: Option<PathBuf>,
+        /// Number of worker threads [default: one per core]
+        #[structopt(short, long)]
+        jobs: Option<usize>,
+    },
+}
+
+// ============================================================================
+// Output Arguments
+// ============================================================================
+
+#[derive(Debug, StructOpt)]
+pub struct OutputArgs {
+    /// Number of generations to simulate
+    #[structopt(short, long, default_value = "100")]
+    pub generations: u32,
+    /// Directory the run output is written to
+    #[structopt(short, long, parse(from_os_str))]
+    pub output: Option<PathBuf>,
+    /// Format of the generation statistics, csv or jsonl
+    #[structopt(short, long, default_value = "csv")]
+    pub format: StatsFormat,
+}
+
+// ============================================================================
+// Resume Arguments
+// ============================================================================
+
+#[derive(Debug, Default, StructOpt)]
+pub struct ResumeArgs {
+    /// Snapshot to continue from, the settings options are ignored
+    #[structopt(long, parse(from_os_str))]
+    pub resume: Option<PathBuf>,
+}
+
+impl ResumeArgs {
+    /// Core restored from the snapshot, or a new one from `settings`
+    pub fn core(&self, settings: &SettingsArgs) -> Result<SimulationCore, Box<dyn Error>> {
+        Ok(match &self.resume {
+            Some(path) => snapshot::load(path)?,
+            None => SimulationCore::new(settings.settings()?)?,
+        })
+    }
+}
+
+// ============================================================================
+// Settings Arguments
+// ============================================================================
+
+// Overrides for the default settings, kept as plain comment so structopt
+// does not use it as the subcommand description
+#[derive(Debug, Default, StructOpt)]
+pub struct SettingsArgs {
+    /// TOML settings file, overridden by the options below
+    #[structopt(short, long, parse(from_os_str))]
+    pub config: Option<PathBuf>,
+    /// Seed for the simulation RNG, at most 2^63 - 1 [default: random]
+    #[structopt(long, parse(try_from_str = parse_seed))]
+    pub seed: Option<u64>,
+    /// Window width in pixels, at most the width of the map
+    #[structopt(long)]
+    pub width: Option<f32>,
+    /// Window height in pixels, at most the height of the map
+    #[structopt(long)]
+    pub height: Option<f32>,
+    /// World size in pixels as WIDTH,HEIGHT, the map adds a tile of wall on
+    /// every side
+    #[structopt(long, parse(try_from_str = parse_pair))]
+    pub world: Option<(f32, f32)>,
+    /// Frames rendered per second
+    #[structopt(long)]
+    pub fps: Option<u32>,
+    /// Simulation ticks per simulated second
+    #[structopt(long)]
+    pub tick_rate: Option<u32>,
+    /// Number of blobs in the first generation
+    #[structopt(long)]
+    pub start_blobs: Option<u32>,
+    /// Energy gained per food, scaled by blob size
+    #[structopt(long)]
+    pub food_energy: Option<f32>,
+    /// Duration of a generation in seconds
+    #[structopt(long)]
+    pub gen_duration: Option<u32>,
+    /// Food per generation as START,STEP,MIN
+    #[structopt(long, parse(try_from_str = parse_triple))]
+    pub food_decay: Option<(u32, u32, u32)>,
+    /// Let blobs out in the field at the end of a generation survive
+    #[structopt(long)]
+    pub no_return_home: bool,
+    /// Starting energy of a blob, scaled by its size
+    #[structopt(long)]
+    pub blob_energy: Option<f32>,
+    /// Blob speed as BASE,MUTATION
+    #[structopt(long, parse(try_from_str = parse_pair))]
+    pub speed: Option<(f32, f32)>,
+    /// Blob sense radius as BASE,MUTATION
+    #[structopt(long, parse(try_from_str = parse_pair))]
+    pub sense: Option<(f32, f32)>,
+    /// Blob size as BASE,MUTATION
+    #[structopt(long, parse(try_from_str = parse_pair))]
+    pub size: Option<(f32, f32)>,
+    /// Let larger blobs hunt smaller ones
+    #[structopt(long)]
+    pub predation: bool,
+    /// Factor a blob has to be larger than another to eat it
+    #[structopt(long)]
+    pub predation_ratio: Option<f32>,
+    /// Reproduction mode, asexual or sexual
+    #[structopt(long)]
+    pub reproduction: Option<Reproduction>,
+    /// Crossover of sexual reproduction, average, random_parent or blend
+    #[structopt(long)]
+    pub crossover: Option<Crossover>,
+    /// Mutation model, uniform, gaussian, log_normal, per_gene:PROBABILITY or
+    /// none
+    #[structopt(long)]
+    pub mutation: Option<Mutation>,
+}
+
+impl SettingsArgs {
+    /// Config file or default settings with all given arguments applied
+    pub fn settings(&self) -> Result<Settings, SettingsError> {
+        let mut settings = match &self.config {
+            Some(path) => Settings::load(path)?,
+            None => Settings::default(),
+        };
+        self.apply(&mut settings);
+        settings.validate()?;
+        Ok(settings)
+    }
+
+    pub fn apply(&self, settings: &mut Settings) {
+        if self.seed.is_some() {
+            settings.set_seed(self.seed);
+        }
+        if self.width.is_some() || self.height.is_some() {
+            let screen_size = settings.screen_size();
+            settings.set_screen_size((
+                self.width.unwrap_or(screen_size.0),
+                self.height.unwrap_or(screen_size.1),
+            ));
+        }
+        if let Some(world) = self.world {
+            settings.set_world_size(world);
+        }
+        if let Some(fps) = self.fps {
+            settings.set_fps(fps);
+        }
+        if let Some(tick_rate) = self.tick_rate {
+            settings.set_tick_rate(tick_rate);
+        }
+        if let Some(start_blobs) = self.start_blobs {
+            settings.set_start_blobs(start_blobs);
+        }
+        if let Some(food_energy) = self.food_energy {
+            settings.set_food_energy(food_energy);
+        }
+        if let Some(gen_duration) = self.gen_duration {
+            settings.set_gen_duration(gen_duration);
+        }
+        if let Some(food_decay) = self.food_decay {
+            settings.set_food_decay(food_decay);
+        }
+        if self.no_return_home {
+            settings.set_return_home(false);
+        }
+        if let Some(blob_energy) = self.blob_energy {
+            settings.set_blob_energy(blob_energy);
+        }
+        for (name, gene) in [
+            ("speed", self.speed),
+            ("sense", self.sense),
+            ("size", self.size),
+        ] {
+            if let (Some((base, mutation)), Some(g)) = (gene, settings.gene_mut(name)) {
+                g.set_base(base);
+                g.set_mutation(mutation);
+            }
+        }
+        if self.predation {
+            settings.set_predation(true);
+        }
+        if let Some(ratio) = self.predation_ratio {
+            settings.set_predation_ratio(ratio);
+        }
+        if let Some(reproduction) = self.reproduction {
+            settings.set_reproduction(reproduction);
+        }
+        if let Some(crossover) = self.crossover {
+            settings.set_crossover(crossover);
+        }
+        if let Some(mutation) = &self.mutation {
+            settings.set_mutation(mutation.clone());
+        }
+    }
+}
+
+// ============================================================================
+// Parsers
+// ============================================================================
+
+fn parse_values<T>(s: &str, count: usize) -> Result<Vec<T>, String>
+where
+    T: FromStr,
+    T::Err: Display,
+{
+    let values = s
+        .split(',')
+        .map(|v| v.trim().parse::<T>().map_err(|e| format!("{}: {}", v, e)))
+        .collect::<Result<Vec<T>, String>>()?;
+    if values.len() == count {
+        Ok(values)
+    } else {
+        Err(format!("expected {} comma separated values", count))
+    }
+}
+
+fn parse_seed(s: &str) -> Result<u64, String> {
+    match s.parse::<u64>().map_err(|e| e.to_string())? {
+        seed if seed <= MAX_SEED => Ok(seed),
+        _ => Err(format!("seed has to be at most {}", MAX_SEED)),
+    }
+}
+
+fn parse_pair(s: &str) -> Result<(f32, f32), String> {
+    let values = parse_values(s, 2)?;
+    Ok((values[0], values[1]))
+}
+
+fn parse_triple(s: &str) -> Result<(u32, u32, u32), String> {
+    let values = parse_values(s, 3)?;
+    Ok((values[0], values[1], values[2]))
+}
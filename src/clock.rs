@@ -0,0 +1,268 @@
+//! blob-sim clock
+//!
+//! Decides how many simulation ticks the frontend runs per rendered frame.
+//! Ticks happen at the fixed [`tick_rate`](super::settings::Settings::tick_rate)
+//! in simulated time no matter how many frames are rendered, in between
+//! ticks blobs are drawn interpolated by [`Clock::alpha`].
+
+// ============================================================================
+// Imports
+// ============================================================================
+
+use ggez::timer;
+use std::{
+    fmt,
+    time::{Duration, Instant},
+};
+
+// ============================================================================
+// Constants
+// ============================================================================
+
+/// Selectable time scales, relative to real time
+pub const SPEEDS: [f32; 9] = [0.25, 0.5, 1.0, 2.0, 4.0, 8.0, 16.0, 32.0, 64.0];
+/// Wall time spent simulating before a frame is rendered
+///
+/// When the ticks due don't fit into it the rest is dropped, so a frame never
+/// takes much longer than this, and uncapped runs simulate this long per frame.
+/// The clock then shows the speed actually reached.
+pub const FRAME_BUDGET: Duration = Duration::from_millis(100);
+/// Wall time the reached speed is measured over
+pub const SPEED_WINDOW: Duration = Duration::from_secs(1);
+
+// ============================================================================
+// Frame Budget
+// ============================================================================
+
+/// Runs `step` up to `ticks` times while `elapsed` is below the
+/// [`FRAME_BUDGET`], returns how often it ran
+pub fn run_within_budget<E, S>(ticks: u32, elapsed: E, mut step: S) -> u32
+where
+    E: Fn() -> Duration,
+    S: FnMut(),
+{
+    let mut ran = 0;
+    while ran < ticks && elapsed() < FRAME_BUDGET {
+        step();
+        ran += 1;
+    }
+    ran
+}
+
+// ============================================================================
+// Clock
+// ============================================================================
+
+pub struct Clock {
+    /// Index into [`SPEEDS`]
+    speed: usize,
+    uncapped: bool,
+    /// Ticks due but not run yet
+    residual: f64,
+    last_frame: Instant,
+    // Reached speed
+    /// Wall time and ticks run in the current measurement window
+    window: (Duration, u64),
+    reached: Option<f32>,
+}
+
+impl Clock {
+    pub fn new() -> Clock {
+        Clock {
+            speed: 2,
+            uncapped: false,
+            residual: 0.0,
+            last_frame: Instant::now(),
+            window: (Duration::ZERO, 0),
+            reached: None,
+        }
+    }
+
+    #[inline(always)]
+    pub fn speed(&self) -> f32 {
+        SPEEDS[self.speed]
+    }
+    pub fn faster(&mut self) {
+        self.speed = (self.speed + 1).min(SPEEDS.len() - 1);
+    }
+    pub fn slower(&mut self) {
+        self.speed = self.speed.saturating_sub(1);
+    }
+
+    /// Whether ticks run as fast as possible, only interrupted to render
+    #[inline(always)]
+    pub fn uncapped(&self) -> bool {
+        self.uncapped
+    }
+    #[inline(always)]
+    pub fn toggle_uncapped(&mut self) {
+        self.uncapped = !self.uncapped;
+    }
+
+    /// Forgets the ticks due, e.g. while paused
+    pub fn reset(&mut self) {
+        self.residual = 0.0;
+        self.window = (Duration::ZERO, 0);
+        self.reached = None;
+    }
+
+    /// Speed actually reached over the last [`SPEED_WINDOW`], relative to
+    /// real time, lower than [`Clock::speed`] if ticks had to be dropped
+    #[inline(always)]
+    pub fn reached(&self) -> Option<f32> {
+        self.reached
+    }
+    /// Counts `ticks` run since the last call of [`Clock::ticks`]
+    pub fn ran(&mut self, ticks: u32) {
+        self.window.1 += u64::from(ticks);
+    }
+
+    /// Number of ticks due after `elapsed` wall time at `tick_rate` ticks per
+    /// second of real time
+    ///
+    /// Uncapped there is no limit, the caller stops at the [`FRAME_BUDGET`].
+    pub fn ticks(&mut self, elapsed: Duration, tick_rate: u32) -> u32 {
+        if self.window.0 >= SPEED_WINDOW {
+            let real_time = self.window.0.as_secs_f64() * f64::from(tick_rate);
+            self.reached = Some((self.window.1 as f64 / real_time) as f32);
+            self.window = (Duration::ZERO, 0);
+        }
+        self.window.0 += elapsed;
+        if self.uncapped {
+            self.residual = 0.0;
+            return u32::MAX;
+        }
+        self.residual += elapsed.as_secs_f64() * f64::from(tick_rate) * f64::from(self.speed());
+        let ticks = self.residual.floor();
+        self.residual -= ticks;
+        ticks as u32
+    }
+
+    /// Fraction of the next tick that is already due, `1.0` if uncapped
+    pub fn alpha(&self) -> f32 {
+        if self.uncapped {
+            1.0
+        } else {
+            self.residual as f32
+        }
+    }
+
+    /// Sleeps until at least a frame at `fps` frames per second has passed
+    /// since the last call, unless uncapped
+    pub fn limit_frame_rate(&mut self, fps: u32) {
+        if !self.uncapped && fps > 0 {
+            let frame = Duration::from_secs_f64(1.0 / f64::from(fps));
+            if let Some(rest) = frame.checked_sub(self.last_frame.elapsed()) {
+                timer::sleep(rest);
+            }
+        }
+        self.last_frame = Instant::now();
+    }
+}
+
+impl Default for Clock {
+    fn default() -> Clock {
+        Clock::new()
+    }
+}
+
+impl fmt::Display for Clock {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.reached {
+            Some(reached) if self.uncapped => write!(f, "uncapped ({:.1}x)", reached),
+            None if self.uncapped => write!(f, "uncapped"),
+            // Dropping ticks, show how fast it actually runs
+            Some(reached) if reached < self.speed() * 0.95 => {
+                write!(f, "{}x ({:.1}x reached)", self.speed(), reached)
+            }
+            _ => write!(f, "{}x", self.speed()),
+        }
+    }
+}
+
+// ============================================================================
+// Testing
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::cell::Cell;
+
+    fn ms(millis: u64) -> Duration {
+        Duration::from_millis(millis)
+    }
+
+    #[test]
+    fn ticks_accumulate_at_the_tick_rate() {
+        let mut clock = Clock::new();
+        // Half a tick per frame at 4 ticks a second
+        assert_eq!(clock.ticks(ms(125), 4), 0);
+        assert_eq!(clock.alpha(), 0.5);
+        assert_eq!(clock.ticks(ms(125), 4), 1);
+        assert_eq!(clock.alpha(), 0.0);
+        assert_eq!(clock.ticks(ms(1000), 60), 60);
+        clock.faster();
+        assert_eq!(clock.speed(), 2.0);
+        assert_eq!(clock.ticks(ms(375), 4), 3);
+        assert_eq!(clock.ticks(ms(1000), 60), 120);
+        clock.slower();
+        clock.slower();
+        assert_eq!(clock.ticks(ms(375), 4), 0);
+        assert_eq!(clock.alpha(), 0.75);
+        clock.reset();
+        assert_eq!(clock.alpha(), 0.0);
+    }
+
+    #[test]
+    fn uncapped_ticks_are_unlimited() {
+        let mut clock = Clock::new();
+        clock.ticks(ms(125), 4);
+        clock.toggle_uncapped();
+        assert_eq!(clock.ticks(ms(1), 4), u32::MAX);
+        assert_eq!(clock.alpha(), 1.0);
+        clock.toggle_uncapped();
+        // Nothing was left over from before
+        assert_eq!(clock.ticks(ms(125), 4), 0);
+    }
+
+    #[test]
+    fn ticks_beyond_the_budget_are_dropped() {
+        // Every tick takes 30 ms
+        let elapsed = Cell::new(Duration::ZERO);
+        let step = || elapsed.set(elapsed.get() + ms(30));
+        assert_eq!(run_within_budget(10, || elapsed.get(), step), 4);
+        elapsed.set(Duration::ZERO);
+        assert_eq!(run_within_budget(3, || elapsed.get(), step), 3);
+        assert_eq!(run_within_budget(u32::MAX, || FRAME_BUDGET, || {}), 0);
+    }
+
+    #[test]
+    fn reached_speed_is_measured_over_a_window() {
+        let mut clock = Clock::new();
+        for _ in 0..10 {
+            let ticks = clock.ticks(ms(100), 60);
+            assert_eq!(ticks, 6);
+            // Only half of the ticks fit
+            clock.ran(ticks / 2);
+        }
+        assert_eq!(clock.reached(), None);
+        assert_eq!(clock.to_string(), "1x");
+        clock.ticks(ms(100), 60);
+        assert_eq!(clock.reached(), Some(0.5));
+        assert_eq!(clock.to_string(), "1x (0.5x reached)");
+        // Keeping up again
+        clock.ran(6);
+        for _ in 0..10 {
+            let ticks = clock.ticks(ms(100), 60);
+            clock.ran(ticks);
+        }
+        assert_eq!(clock.reached(), Some(1.0));
+        assert_eq!(clock.to_string(), "1x");
+        clock.toggle_uncapped();
+        assert_eq!(clock.to_string(), "uncapped (1.0x)");
+        clock.reset();
+        assert_eq!(clock.reached(), None);
+        assert_eq!(clock.to_string(), "uncapped");
+    }
+}
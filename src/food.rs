@@ -0,0 +1,95 @@
+//! blob-sim food
+//!
+
+// ============================================================================
+// Imports
+// ============================================================================
+
+use super::grid::SpatialGrid;
+use ggez::nalgebra::Point2;
+use serde::{Deserialize, Serialize};
+
+// ============================================================================
+// Food Id
+// ============================================================================
+
+/// Stable handle of a piece of food, unique within its [`FoodGrid`]
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+pub struct FoodId(usize);
+
+// ============================================================================
+// Food Grid
+// ============================================================================
+
+/// Food in the world, bucketed into square cells of a [`SpatialGrid`] so
+/// the nearest food stays cheap to find no matter how much is placed
+#[derive(Clone, Serialize, Deserialize)]
+pub struct FoodGrid {
+    grid: SpatialGrid<FoodId>,
+    food: Vec<Option<Point2<f32>>>,
+    len: usize,
+}
+
+impl FoodGrid {
+    pub fn new(world_size: (f32, f32), cell_size: f32) -> FoodGrid {
+        FoodGrid {
+            grid: SpatialGrid::new(world_size, cell_size),
+            food: vec![],
+            len: 0,
+        }
+    }
+
+    /// Removes all food, ids start over at zero
+    pub fn clear(&mut self) {
+        self.grid.clear();
+        self.food.clear();
+        self.len = 0;
+    }
+
+    pub fn insert(&mut self, position: Point2<f32>) -> FoodId {
+        let id = FoodId(self.food.len());
+        self.grid.insert(id, position);
+        self.food.push(Some(position));
+        self.len += 1;
+        id
+    }
+
+    pub fn remove(&mut self, id: FoodId) -> Option<Point2<f32>> {
+        let position = self.food.get_mut(id.0).and_then(Option::take)?;
+        self.grid.remove(id, position);
+        self.len -= 1;
+        Some(position)
+    }
+
+    #[inline(always)]
+    pub fn get(&self, id: FoodId) -> Option<Point2<f32>> {
+        self.food.get(id.0).copied().flatten()
+    }
+    #[inline(always)]
+    pub fn contains(&self, id: FoodId) -> bool {
+        self.get(id).is_some()
+    }
+    #[inline(always)]
+    pub fn len(&self) -> usize {
+        self.len
+    }
+    #[inline(always)]
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// All remaining food in order of placement
+    pub fn iter(&self) -> impl Iterator<Item = (FoodId, Point2<f32>)> + '_ {
+        self.food
+            .iter()
+            .enumerate()
+            .filter_map(|(id, food)| food.map(|food| (FoodId(id), food)))
+    }
+
+    /// Nearest food within `radius` of `position`
+    ///
+    /// Food at equal distance is ordered by id, i.e. the earlier placed wins.
+    pub fn nearest(&self, position: Point2<f32>, radius: f32) -> Option<(FoodId, Point2<f32>)> {
+        self.grid.nearest(position, radius, |_| true)
+    }
+}
@@ -0,0 +1,416 @@
+//! blob-sim genome
+//!
+//! Every trait of a blob is a gene. The genes of a simulation are defined in
+//! its [`Settings`](super::settings::Settings) with a base value, the spread
+//! of their mutations, optional bounds and their share in the energy cost of
+//! moving. Each blob carries a [`Genome`] with one value per gene, how an
+//! offspring's genome mutates is up to the [`mutation`] model.
+
+// ============================================================================
+// Modules
+// ============================================================================
+
+pub mod mutation;
+
+// ============================================================================
+// Imports
+// ============================================================================
+
+use super::settings::Crossover;
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+use std::{convert::TryFrom, error, fmt, ops::Deref, ops::Index};
+
+// ============================================================================
+// Constants
+// ============================================================================
+
+/// Genes the blob behaviour is built on, always the first ones of a genome
+pub const BUILTIN: [&str; 3] = ["speed", "sense", "size"];
+/// Index of the speed gene
+pub const SPEED: usize = 0;
+/// Index of the sense radius gene
+pub const SENSE: usize = 1;
+/// Index of the size gene
+pub const SIZE: usize = 2;
+/// Lowest value of a gene without a `min`, mutations never take genes to
+/// zero or below unless the gene allows it
+pub const FLOOR: f32 = 0.001;
+
+// ============================================================================
+// Gene
+// ============================================================================
+
+/// Definition of a single named gene
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Gene {
+    name: String,
+    base: f32,
+    mutation: f32,
+    min: Option<f32>,
+    max: Option<f32>,
+    #[serde(default)]
+    cost: f32,
+}
+
+impl Gene {
+    /// Unbounded gene free to carry around
+    pub fn new(name: &str, base: f32, mutation: f32) -> Gene {
+        Gene {
+            name: name.to_owned(),
+            base,
+            mutation,
+            min: None,
+            max: None,
+            cost: 0.0,
+        }
+    }
+
+    #[inline(always)]
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+    /// Value of the first generation
+    #[inline(always)]
+    pub fn base(&self) -> f32 {
+        self.base
+    }
+    #[inline(always)]
+    pub fn set_base(&mut self, base: f32) {
+        self.base = base;
+    }
+    /// Width of the mutation distribution, relative to the value, see
+    /// [`Mutation`](mutation::Mutation) for how it is used
+    #[inline(always)]
+    pub fn mutation(&self) -> f32 {
+        self.mutation
+    }
+    #[inline(always)]
+    pub fn set_mutation(&mut self, mutation: f32) {
+        self.mutation = mutation;
+    }
+    /// Lowest and highest value, mutations outside are clamped
+    ///
+    /// Without a lowest value mutations are clamped to [`FLOOR`].
+    #[inline(always)]
+    pub fn bounds(&self) -> (Option<f32>, Option<f32>) {
+        (self.min, self.max)
+    }
+    #[inline(always)]
+    pub fn set_bounds(&mut self, bounds: (Option<f32>, Option<f32>)) {
+        self.min = bounds.0;
+        self.max = bounds.1;
+    }
+    /// Exponent of the gene in the energy cost of moving, `0` for none
+    #[inline(always)]
+    pub fn cost(&self) -> f32 {
+        self.cost
+    }
+    #[inline(always)]
+    pub fn set_cost(&mut self, cost: f32) {
+        self.cost = cost;
+    }
+
+    /// Checks that the bounds are in order and hold the base, and that the
+    /// mutation and cost are finite and not negative
+    pub fn validate(&self) -> Result<(), GenomeError> {
+        let (min, max) = (
+            self.min.unwrap_or(f32::NEG_INFINITY),
+            self.max.unwrap_or(f32::INFINITY),
+        );
+        if min.is_nan() || max.is_nan() || min > max {
+            return Err(GenomeError::Bounds(self.name.clone()));
+        }
+        if !self.base.is_finite() || self.base < min || self.base > max {
+            return Err(GenomeError::Base(self.name.clone()));
+        }
+        for (field, value) in [("mutation", self.mutation), ("cost", self.cost)] {
+            if !value.is_finite() || value < 0.0 {
+                return Err(GenomeError::Value(self.name.clone(), field));
+            }
+        }
+        Ok(())
+    }
+
+    /// `value` moved into the bounds
+    pub fn clamp(&self, value: f32) -> f32 {
+        let value = value.max(self.min.unwrap_or(FLOOR));
+        self.max.map_or(value, |max| value.min(max))
+    }
+
+    /// Factor `value` adds to the energy cost of moving
+    fn cost_factor(&self, value: f32) -> f32 {
+        if self.cost.fract() == 0.0 {
+            value.powi(self.cost as i32)
+        } else {
+            value.powf(self.cost)
+        }
+    }
+}
+
+// ============================================================================
+// Genes
+// ============================================================================
+
+/// Gene definitions of a simulation
+///
+/// Always contains the [`BUILTIN`] genes, in that order and before any other
+/// gene, so their values can be found at [`SPEED`], [`SENSE`] and [`SIZE`].
+/// Config files list the genes in any order, they are sorted on load.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(try_from = "Vec<Gene>", into = "Vec<Gene>")]
+pub struct Genes(Vec<Gene>);
+
+impl Genes {
+    /// Index of the gene called `name`
+    pub fn index(&self, name: &str) -> Option<usize> {
+        self.0.iter().position(|gene| gene.name == name)
+    }
+    /// Gene called `name`
+    pub fn gene(&self, name: &str) -> Option<&Gene> {
+        self.index(name).map(|i| &self.0[i])
+    }
+    pub fn gene_mut(&mut self, name: &str) -> Option<&mut Gene> {
+        self.index(name).map(move |i| &mut self.0[i])
+    }
+}
+
+impl Deref for Genes {
+    type Target = [Gene];
+
+    fn deref(&self) -> &[Gene] {
+        &self.0
+    }
+}
+
+impl TryFrom<Vec<Gene>> for Genes {
+    type Error = GenomeError;
+
+    fn try_from(mut genes: Vec<Gene>) -> Result<Genes, GenomeError> {
+        for (i, gene) in genes.iter().enumerate() {
+            if genes[..i].iter().any(|other| other.name == gene.name) {
+                return Err(GenomeError::Duplicate(gene.name.clone()));
+            }
+            gene.validate()?;
+        }
+        let mut sorted = vec![];
+        for name in BUILTIN.iter() {
+            match genes.iter().position(|gene| gene.name == *name) {
+                Some(i) => sorted.push(genes.remove(i)),
+                None => return Err(GenomeError::Missing(name.to_string())),
+            }
+        }
+        sorted.append(&mut genes);
+        Ok(Genes(sorted))
+    }
+}
+
+impl From<Genes> for Vec<Gene> {
+    fn from(genes: Genes) -> Vec<Gene> {
+        genes.0
+    }
+}
+
+// ============================================================================
+// Genome
+// ============================================================================
+
+/// Gene values of a single blob, in the order of the [`Genes`]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Genome(Vec<f32>);
+
+impl Genome {
+    /// Genome of the first generation
+    pub fn new(genes: &Genes) -> Genome {
+        Genome(genes.iter().map(Gene::base).collect())
+    }
+
+    #[inline(always)]
+    pub fn values(&self) -> &[f32] {
+        &self.0
+    }
+
+    /// Crossover of this genome and `other`, gene by gene
+    pub fn cross<R: Rng + ?Sized>(
+        &self,
+        other: &Genome,
+        crossover: Crossover,
+        rng: &mut R,
+    ) -> Genome {
+        Genome(
+            self.0
+                .iter()
+                .zip(&other.0)
+                .map(|(&a, &b)| match crossover {
+                    Crossover::Average => (a + b) / 2.0,
+                    Crossover::RandomParent => {
+                        if rng.gen::<bool>() {
+                            a
+                        } else {
+                            b
+                        }
+                    }
+                    Crossover::Blend => a + (b - a) * rng.gen::<f32>(),
+                })
+                .collect(),
+        )
+    }
+
+    /// Energy needed to move `distance`, scaled by every gene with a cost
+    pub fn move_cost(&self, genes: &Genes, distance: f32) -> f32 {
+        genes
+            .iter()
+            .zip(&self.0)
+            .filter(|(gene, _)| gene.cost != 0.0)
+            .fold(distance, |cost, (gene, &value)| {
+                cost * gene.cost_factor(value)
+            })
+    }
+}
+
+impl Index<usize> for Genome {
+    type Output = f32;
+
+    fn index(&self, i: usize) -> &f32 {
+        &self.0[i]
+    }
+}
+
+// ============================================================================
+// Genome Error
+// ============================================================================
+
+#[derive(Debug)]
+pub enum GenomeError {
+    /// A built-in gene is not defined
+    Missing(String),
+    /// Two genes share a name
+    Duplicate(String),
+    /// The lowest value of a gene is above its highest
+    Bounds(String),
+    /// The base value of a gene lies outside its bounds
+    Base(String),
+    /// The named field of a gene is negative or not finite
+    Value(String, &'static str),
+}
+
+impl fmt::Display for GenomeError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            GenomeError::Missing(name) => write!(f, "missing gene {}", name),
+            GenomeError::Duplicate(name) => write!(f, "duplicate gene {}", name),
+            GenomeError::Bounds(name) => write!(f, "gene {} has min above max", name),
+            GenomeError::Base(name) => write!(f, "gene {} has its base out of bounds", name),
+            GenomeError::Value(name, field) => {
+                write!(f, "gene {} needs a finite {} of at least 0", name, field)
+            }
+        }
+    }
+}
+
+impl error::Error for GenomeError {}
+
+// ============================================================================
+// Testing
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::simulation::SimulationRng;
+    use rand::SeedableRng;
+
+    fn genes(names: &[&str]) -> Vec<Gene> {
+        names.iter().map(|name| Gene::new(name, 1.0, 0.5)).collect()
+    }
+
+    fn names(genes: &Genes) -> Vec<&str> {
+        genes.iter().map(Gene::name).collect()
+    }
+
+    #[test]
+    fn builtin_genes_come_first() {
+        let genes = Genes::try_from(genes(&["wings", "size", "horns", "speed", "sense"])).unwrap();
+        assert_eq!(names(&genes), ["speed", "sense", "size", "wings", "horns"]);
+        assert_eq!(genes.index("size"), Some(SIZE));
+        assert_eq!(genes.index("wings"), Some(3));
+    }
+
+    #[test]
+    fn missing_and_duplicate_genes_are_rejected() {
+        assert!(matches!(
+            Genes::try_from(genes(&["speed", "size"])),
+            Err(GenomeError::Missing(name)) if name == "sense"
+        ));
+        assert!(matches!(
+            Genes::try_from(genes(&["speed", "sense", "size", "speed"])),
+            Err(GenomeError::Duplicate(name)) if name == "speed"
+        ));
+    }
+
+    #[test]
+    fn unfitting_genes_are_rejected() {
+        let check = |change: fn(&mut Gene)| {
+            let mut genes = genes(&["speed", "sense", "size"]);
+            change(&mut genes[1]);
+            Genes::try_from(genes)
+        };
+        assert!(check(|g| g.set_bounds((Some(0.5), Some(2.0)))).is_ok());
+        assert!(matches!(
+            check(|g| g.set_bounds((Some(2.0), Some(0.5)))),
+            Err(GenomeError::Bounds(_))
+        ));
+        assert!(matches!(
+            check(|g| g.set_bounds((Some(1.5), None))),
+            Err(GenomeError::Base(_))
+        ));
+        assert!(matches!(
+            check(|g| g.set_base(f32::INFINITY)),
+            Err(GenomeError::Base(_))
+        ));
+        assert!(matches!(
+            check(|g| g.set_mutation(-0.1)),
+            Err(GenomeError::Value(_, "mutation"))
+        ));
+        assert!(matches!(
+            check(|g| g.set_cost(f32::NAN)),
+            Err(GenomeError::Value(_, "cost"))
+        ));
+    }
+
+    #[test]
+    fn crossover_mixes_both_parents() {
+        let genes = Genes::try_from(genes(&["speed", "sense", "size"])).unwrap();
+        let a = Genome::new(&genes);
+        let b = Genome(vec![3.0, 5.0, 2.0]);
+        let mut rng = SimulationRng::seed_from_u64(4);
+        assert_eq!(
+            a.cross(&b, Crossover::Average, &mut rng).values(),
+            [2.0, 3.0, 1.5]
+        );
+        for _ in 0..20 {
+            let child = a.cross(&b, Crossover::RandomParent, &mut rng);
+            for (i, &v) in child.values().iter().enumerate() {
+                assert!(v == a[i] || v == b[i]);
+            }
+            let child = a.cross(&b, Crossover::Blend, &mut rng);
+            for (i, &v) in child.values().iter().enumerate() {
+                assert!(v >= a[i].min(b[i]) && v <= a[i].max(b[i]));
+            }
+        }
+    }
+
+    #[test]
+    fn move_cost_scales_by_genes_with_cost() {
+        let mut genes = genes(&["speed", "sense", "size", "wings"]);
+        genes[0].set_cost(2.0);
+        genes[2].set_cost(3.0);
+        genes[3].set_cost(0.5);
+        let genes = Genes::try_from(genes).unwrap();
+        let genome = Genome(vec![2.0, 10.0, 0.5, 4.0]);
+        // 10 * 2^2 * 0.5^3 * 4^0.5, sense is free
+        assert_eq!(genome.move_cost(&genes, 10.0), 10.0);
+        assert_eq!(Genome::new(&genes).move_cost(&genes, 10.0), 10.0);
+    }
+}
@@ -0,0 +1,355 @@
+//! blob-sim mutation models
+//!
+//! A [`MutationModel`] changes the genome of every offspring after the
+//! crossover. Which model a simulation uses is set by its [`Mutation`]
+//! setting, the models read the spread of each gene from the gene's
+//! `mutation` width. Mutated values are always clamped to the gene bounds.
+
+// ============================================================================
+// Imports
+// ============================================================================
+
+use super::{Gene, Genes, Genome};
+use rand::{Rng, RngCore};
+use rand_distr::StandardNormal;
+use serde::{Deserialize, Serialize};
+use std::{convert::TryFrom, str::FromStr};
+
+// ============================================================================
+// Mutation Model
+// ============================================================================
+
+pub trait MutationModel {
+    /// Mutates the `genome` of an offspring in place
+    fn mutate(&self, genome: &mut Genome, genes: &Genes, rng: &mut dyn RngCore);
+}
+
+/// One gene chosen at random, scaled by a uniform factor within
+/// `1 ± mutation / 2`
+pub struct UniformMutation;
+
+impl MutationModel for UniformMutation {
+    fn mutate(&self, genome: &mut Genome, genes: &Genes, rng: &mut dyn RngCore) {
+        let i = rng.gen::<usize>() % genes.len();
+        genome.0[i] = genes[i].clamp(genome.0[i] * uniform(&genes[i], rng));
+    }
+}
+
+/// One gene chosen at random, scaled by a normal factor around `1` with a
+/// standard deviation of `mutation / 2`
+pub struct GaussianMutation;
+
+impl MutationModel for GaussianMutation {
+    fn mutate(&self, genome: &mut Genome, genes: &Genes, rng: &mut dyn RngCore) {
+        let i = rng.gen::<usize>() % genes.len();
+        let factor = 1.0 + normal(rng) * genes[i].mutation() / 2.0;
+        genome.0[i] = genes[i].clamp(genome.0[i] * factor);
+    }
+}
+
+/// One gene chosen at random, scaled by `e^x` with `x` normal around `0` with
+/// a standard deviation of `mutation / 2`, so values never change their sign
+pub struct LogNormalMutation;
+
+impl MutationModel for LogNormalMutation {
+    fn mutate(&self, genome: &mut Genome, genes: &Genes, rng: &mut dyn RngCore) {
+        let i = rng.gen::<usize>() % genes.len();
+        let factor = (normal(rng) * genes[i].mutation() / 2.0).exp();
+        genome.0[i] = genes[i].clamp(genome.0[i] * factor);
+    }
+}
+
+/// Every gene independently with `probability`, scaled like
+/// [`UniformMutation`]
+#[derive(Debug, Clone, PartialEq)]
+pub struct PerGeneMutation {
+    pub probability: f32,
+}
+
+impl MutationModel for PerGeneMutation {
+    fn mutate(&self, genome: &mut Genome, genes: &Genes, rng: &mut dyn RngCore) {
+        for (value, gene) in genome.0.iter_mut().zip(genes.iter()) {
+            if rng.gen::<f32>() < self.probability {
+                *value = gene.clamp(*value * uniform(gene, rng));
+            }
+        }
+    }
+}
+
+/// All genes at once, scaled by `1 + x` with `x` drawn from a multivariate
+/// normal distribution
+///
+/// `matrix` is the covariance of the relative changes, rows and columns in
+/// the order of the genes. It has to be symmetric and positive definite, with
+/// one row for every gene. The gene `mutation` widths are not used.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CovarianceMutation {
+    matrix: Vec<Vec<f32>>,
+    /// Lower triangular `l` with `l * l^T` equal to `matrix`
+    factor: Vec<Vec<f32>>,
+}
+
+impl CovarianceMutation {
+    pub fn new(matrix: Vec<Vec<f32>>) -> Result<CovarianceMutation, String> {
+        let n = matrix.len();
+        if matrix.iter().any(|row| row.len() != n) {
+            return Err("covariance matrix has to be square".to_owned());
+        }
+        if (0..n).any(|i| (0..i).any(|j| matrix[i][j] != matrix[j][i])) {
+            return Err("covariance matrix has to be symmetric".to_owned());
+        }
+        let factor = cholesky(&matrix).ok_or("covariance matrix is not positive definite")?;
+        Ok(CovarianceMutation { matrix, factor })
+    }
+
+    #[inline(always)]
+    pub fn matrix(&self) -> &[Vec<f32>] {
+        &self.matrix
+    }
+}
+
+impl MutationModel for CovarianceMutation {
+    fn mutate(&self, genome: &mut Genome, genes: &Genes, rng: &mut dyn RngCore) {
+        let z: Vec<f32> = (0..self.factor.len()).map(|_| normal(rng)).collect();
+        for (row, (value, gene)) in self
+            .factor
+            .iter()
+            .zip(genome.0.iter_mut().zip(genes.iter()))
+        {
+            let x: f32 = row.iter().zip(&z).map(|(a, b)| a * b).sum();
+            *value = gene.clamp(*value * (1.0 + x));
+        }
+    }
+}
+
+/// Offspring are exact copies, as a control
+pub struct NoMutation;
+
+impl MutationModel for NoMutation {
+    fn mutate(&self, _genome: &mut Genome, _genes: &Genes, _rng: &mut dyn RngCore) {}
+}
+
+// ============================================================================
+// Mutation
+// ============================================================================
+
+/// Mutation model of a simulation, as set in the settings
+///
+/// In a config file the model is a table naming it, along with its
+/// parameters:
+///
+/// ```toml
+/// [mutation]
+/// model = "per_gene"
+/// probability = 0.3
+/// ```
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(try_from = "MutationConfig", into = "MutationConfig")]
+pub enum Mutation {
+    Uniform,
+    Gaussian,
+    LogNormal,
+    PerGene(PerGeneMutation),
+    Covariance(CovarianceMutation),
+    None,
+}
+
+impl Mutation {
+    pub fn model(&self) -> &dyn MutationModel {
+        match self {
+            Mutation::Uniform => &UniformMutation,
+            Mutation::Gaussian => &GaussianMutation,
+            Mutation::LogNormal => &LogNormalMutation,
+            Mutation::PerGene(model) => model,
+            Mutation::Covariance(model) => model,
+            Mutation::None => &NoMutation,
+        }
+    }
+}
+
+impl FromStr for Mutation {
+    type Err = String;
+
+    /// Parses any model but the covariance one, which needs a config file
+    fn from_str(s: &str) -> Result<Mutation, String> {
+        let (model, probability) = match s.split_once(':') {
+            Some(("per_gene", probability)) => (
+                "per_gene",
+                Some(probability.parse().map_err(|e| format!("{}", e))?),
+            ),
+            _ => (s, None),
+        };
+        Mutation::try_from(MutationConfig {
+            model: model.to_owned(),
+            probability,
+            matrix: None,
+        })
+        .map_err(|_| {
+            format!(
+                "unknown mutation {}, use uniform, gaussian, log_normal, per_gene:PROBABILITY or none",
+                s
+            )
+        })
+    }
+}
+
+/// [`Mutation`] as written to config files
+#[derive(Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct MutationConfig {
+    model: String,
+    probability: Option<f32>,
+    matrix: Option<Vec<Vec<f32>>>,
+}
+
+impl TryFrom<MutationConfig> for Mutation {
+    type Error = String;
+
+    fn try_from(config: MutationConfig) -> Result<Mutation, String> {
+        match config.model.as_str() {
+            "uniform" => Ok(Mutation::Uniform),
+            "gaussian" => Ok(Mutation::Gaussian),
+            "log_normal" => Ok(Mutation::LogNormal),
+            "per_gene" => Ok(Mutation::PerGene(PerGeneMutation {
+                probability: config
+                    .probability
+                    .ok_or("per_gene mutation needs a probability")?,
+            })),
+            "covariance" => Ok(Mutation::Covariance(CovarianceMutation::new(
+                config.matrix.ok_or("covariance mutation needs a matrix")?,
+            )?)),
+            "none" => Ok(Mutation::None),
+            model => Err(format!(
+                "unknown mutation {}, use uniform, gaussian, log_normal, per_gene, covariance or none",
+                model
+            )),
+        }
+    }
+}
+
+impl From<Mutation> for MutationConfig {
+    fn from(mutation: Mutation) -> MutationConfig {
+        let (model, probability, matrix) = match mutation {
+            Mutation::Uniform => ("uniform", None, None),
+            Mutation::Gaussian => ("gaussian", None, None),
+            Mutation::LogNormal => ("log_normal", None, None),
+            Mutation::PerGene(model) => ("per_gene", Some(model.probability), None),
+            Mutation::Covariance(model) => ("covariance", None, Some(model.matrix)),
+            Mutation::None => ("none", None, None),
+        };
+        MutationConfig {
+            model: model.to_owned(),
+            probability,
+            matrix,
+        }
+    }
+}
+
+// ============================================================================
+// Helpers
+// ============================================================================
+
+/// Random factor within `1 ± mutation / 2`
+fn uniform(gene: &Gene, rng: &mut dyn RngCore) -> f32 {
+    1.0 + (rng.gen::<f32>() * gene.mutation()) - gene.mutation() / 2.0
+}
+
+/// Lower triangular `l` with `l * l^T` equal to `matrix`, `None` if
+/// `matrix` is not positive definite
+fn cholesky(matrix: &[Vec<f32>]) -> Option<Vec<Vec<f32>>> {
+    let n = matrix.len();
+    let mut l = vec![vec![0.0; n]; n];
+    for i in 0..n {
+        for j in 0..=i {
+            let sum: f32 = l[i][..j].iter().zip(&l[j][..j]).map(|(a, b)| a * b).sum();
+            l[i][j] = if i == j {
+                let variance = matrix[i][i] - sum;
+                if variance <= 0.0 || variance.is_nan() {
+                    return None;
+                }
+                variance.sqrt()
+            } else {
+                (matrix[i][j] - sum) / l[j][j]
+            };
+        }
+    }
+    Some(l)
+}
+
+/// Sample of the standard normal distribution
+fn normal(rng: &mut dyn RngCore) -> f32 {
+    rng.sample(StandardNormal)
+}
+
+// ============================================================================
+// Testing
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::super::FLOOR;
+    use super::*;
+    use crate::settings::Settings;
+    use crate::simulation::SimulationRng;
+    use rand::SeedableRng;
+
+    #[test]
+    fn covariance_matrix_is_checked() {
+        assert!(CovarianceMutation::new(vec![vec![1.0, 0.5], vec![0.5, 1.0]]).is_ok());
+        // Not positive definite
+        assert!(CovarianceMutation::new(vec![vec![1.0, 2.0], vec![2.0, 1.0]]).is_err());
+        assert!(CovarianceMutation::new(vec![vec![0.0, 0.0], vec![0.0, 1.0]]).is_err());
+        // Not symmetric or not square
+        assert!(CovarianceMutation::new(vec![vec![1.0, 0.5], vec![0.0, 1.0]]).is_err());
+        assert!(CovarianceMutation::new(vec![vec![1.0, 0.0], vec![0.0]]).is_err());
+    }
+
+    #[test]
+    fn covariance_matrix_has_to_fit_the_genes() {
+        let mut settings = Settings::default();
+        let matrix = vec![vec![0.04, 0.0], vec![0.0, 0.04]];
+        settings.set_mutation(Mutation::Covariance(
+            CovarianceMutation::new(matrix).unwrap(),
+        ));
+        assert!(settings.validate().is_err());
+        let matrix = vec![
+            vec![0.04, 0.0, 0.0],
+            vec![0.0, 0.04, 0.0],
+            vec![0.0, 0.0, 0.04],
+        ];
+        settings.set_mutation(Mutation::Covariance(
+            CovarianceMutation::new(matrix).unwrap(),
+        ));
+        assert!(settings.validate().is_ok());
+    }
+
+    #[test]
+    fn unbounded_genes_stay_positive() {
+        let mut settings = Settings::default();
+        for name in ["speed", "sense", "size"] {
+            let gene = settings.gene_mut(name).unwrap();
+            gene.set_mutation(8.0);
+            gene.set_bounds((None, None));
+        }
+        let matrix = vec![
+            vec![4.0, 0.0, 0.0],
+            vec![0.0, 4.0, 0.0],
+            vec![0.0, 0.0, 4.0],
+        ];
+        let models = [
+            Mutation::Uniform,
+            Mutation::Gaussian,
+            Mutation::Covariance(CovarianceMutation::new(matrix).unwrap()),
+        ];
+        let mut rng = SimulationRng::seed_from_u64(1);
+        for mutation in &models {
+            let mut genome = Genome::new(settings.genes());
+            for _ in 0..1000 {
+                mutation
+                    .model()
+                    .mutate(&mut genome, settings.genes(), &mut rng);
+                assert!(genome.values().iter().all(|&v| v >= FLOOR));
+            }
+        }
+    }
+}
@@ -0,0 +1,170 @@
+//! blob-sim spatial grid
+//!
+
+// ============================================================================
+// Imports
+// ============================================================================
+
+use ggez::nalgebra::{distance, Point2};
+use serde::{Deserialize, Serialize};
+
+// ============================================================================
+// Spatial Grid
+// ============================================================================
+
+/// Positions of keyed items in the world, bucketed into square cells
+///
+/// Nearest-in-range queries only look at the cells overlapping the query
+/// circle and removal only touches a single cell, so both stay cheap no matter
+/// how many items there are.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct SpatialGrid<K> {
+    cell_size: f32,
+    columns: usize,
+    rows: usize,
+    cells: Vec<Vec<(K, Point2<f32>)>>,
+}
+
+impl<K: Copy + Ord> SpatialGrid<K> {
+    pub fn new(world_size: (f32, f32), cell_size: f32) -> SpatialGrid<K> {
+        let columns = ((world_size.0 / cell_size).ceil() as usize).max(1);
+        let rows = ((world_size.1 / cell_size).ceil() as usize).max(1);
+        SpatialGrid {
+            cell_size,
+            columns,
+            rows,
+            cells: vec![vec![]; columns * rows],
+        }
+    }
+
+    pub fn clear(&mut self) {
+        for cell in &mut self.cells {
+            cell.clear();
+        }
+    }
+
+    pub fn insert(&mut self, key: K, position: Point2<f32>) {
+        let cell = self.cell(position);
+        self.cells[cell].push((key, position));
+    }
+
+    /// Removes `key`, which was inserted at `position`
+    pub fn remove(&mut self, key: K, position: Point2<f32>) {
+        let cell = self.cell(position);
+        self.cells[cell].retain(|&(k, _)| k != key);
+    }
+
+    /// Nearest item within `radius` of `position` matching `filter`
+    ///
+    /// Items at equal distance are ordered by key.
+    pub fn nearest<F: Fn(K) -> bool>(
+        &self,
+        position: Point2<f32>,
+        radius: f32,
+        filter: F,
+    ) -> Option<(K, Point2<f32>)> {
+        let (x0, y0) = self.cell_coords(Point2::new(position.x - radius, position.y - radius));
+        let (x1, y1) = self.cell_coords(Point2::new(position.x + radius, position.y + radius));
+        let mut nearest: Option<(K, Point2<f32>, f32)> = None;
+        for y in y0..=y1 {
+            for x in x0..=x1 {
+                for &(key, item) in &self.cells[y * self.columns + x] {
+                    let d = distance(&position, &item);
+                    if d <= radius
+                        && nearest
+                            .is_none_or(|(n_key, _, n_d)| d < n_d || (d == n_d && key < n_key))
+                        && filter(key)
+                    {
+                        nearest = Some((key, item, d));
+                    }
+                }
+            }
+        }
+        nearest.map(|(key, item, _)| (key, item))
+    }
+
+    fn cell_coords(&self, position: Point2<f32>) -> (usize, usize) {
+        (
+            ((position.x / self.cell_size).max(0.0) as usize).min(self.columns - 1),
+            ((position.y / self.cell_size).max(0.0) as usize).min(self.rows - 1),
+        )
+    }
+
+    fn cell(&self, position: Point2<f32>) -> usize {
+        let (x, y) = self.cell_coords(position);
+        y * self.columns + x
+    }
+}
+
+// ============================================================================
+// Testing
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Ten by ten cells of ten pixels
+    fn grid(items: &[(u32, (f32, f32))]) -> SpatialGrid<u32> {
+        let mut grid = SpatialGrid::new((100.0, 100.0), 10.0);
+        for &(key, (x, y)) in items {
+            grid.insert(key, Point2::new(x, y));
+        }
+        grid
+    }
+
+    fn nearest(grid: &SpatialGrid<u32>, (x, y): (f32, f32), radius: f32) -> Option<u32> {
+        grid.nearest(Point2::new(x, y), radius, |_| true)
+            .map(|(key, _)| key)
+    }
+
+    #[test]
+    fn nearest_looks_across_cell_borders() {
+        let grid = grid(&[(1, (19.5, 5.0)), (2, (34.0, 5.0))]);
+        assert_eq!(nearest(&grid, (20.5, 5.0), 1.0), Some(1));
+        assert_eq!(nearest(&grid, (30.0, 5.0), 1.0), None);
+        assert_eq!(nearest(&grid, (30.0, 5.0), 4.0), Some(2));
+        // Radius is inclusive and spans several cells
+        assert_eq!(nearest(&grid, (45.0, 5.0), 11.0), Some(2));
+        assert_eq!(nearest(&grid, (5.0, 5.0), 14.5), Some(1));
+        assert_eq!(nearest(&grid, (5.0, 5.0), 14.4), None);
+    }
+
+    #[test]
+    fn equal_distances_go_to_the_lower_key() {
+        let grid = grid(&[(5, (20.0, 10.0)), (2, (0.0, 10.0)), (9, (10.0, 0.0))]);
+        assert_eq!(nearest(&grid, (10.0, 10.0), 10.0), Some(2));
+        let found = grid.nearest(Point2::new(10.0, 10.0), 10.0, |key| key != 2);
+        assert_eq!(found, Some((5, Point2::new(20.0, 10.0))));
+    }
+
+    #[test]
+    fn removed_items_are_not_found() {
+        // On the border between two cells
+        let mut grid = grid(&[(1, (10.0, 10.0)), (2, (10.0, 12.0))]);
+        grid.remove(1, Point2::new(10.0, 10.0));
+        assert_eq!(nearest(&grid, (10.0, 9.0), 5.0), Some(2));
+        grid.remove(2, Point2::new(10.0, 12.0));
+        assert_eq!(nearest(&grid, (10.0, 9.0), 5.0), None);
+        grid.insert(1, Point2::new(10.0, 10.0));
+        grid.clear();
+        assert_eq!(nearest(&grid, (10.0, 9.0), 5.0), None);
+    }
+
+    #[test]
+    fn positions_at_the_world_edge_are_kept() {
+        let mut grid = grid(&[
+            (1, (0.0, 0.0)),
+            (2, (100.0, 100.0)),
+            (3, (-1.0, 50.0)),
+            (4, (100.0, 0.0)),
+        ]);
+        assert_eq!(nearest(&grid, (1.0, 1.0), 2.0), Some(1));
+        assert_eq!(nearest(&grid, (99.0, 99.0), 2.0), Some(2));
+        assert_eq!(nearest(&grid, (0.0, 50.0), 2.0), Some(3));
+        // Queries reaching out of the world
+        assert_eq!(nearest(&grid, (150.0, -20.0), 60.0), Some(4));
+        grid.remove(2, Point2::new(100.0, 100.0));
+        assert_eq!(nearest(&grid, (99.0, 99.0), 2.0), None);
+    }
+}
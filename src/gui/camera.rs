@@ -0,0 +1,152 @@
+//! blob-sim camera
+//!
+
+// ============================================================================
+// Imports
+// ============================================================================
+
+use ggez::{
+    graphics::{self, DrawParam},
+    nalgebra::{Point2, Vector2},
+    Context, GameResult,
+};
+
+// ============================================================================
+// Constants
+// ============================================================================
+
+pub const MIN_ZOOM: f32 = 0.1;
+pub const MAX_ZOOM: f32 = 10.0;
+/// Zoom factor of a single scroll wheel step
+pub const ZOOM_STEP: f32 = 1.1;
+/// Distance in pixels the mouse has to move before a click becomes a drag
+pub const DRAG_THRESHOLD: f32 = 4.0;
+
+// ============================================================================
+// Camera
+// ============================================================================
+
+/// View onto the map, `screen = map * zoom + offset`
+///
+/// Map coordinates are the unzoomed pixels the world is laid out in, with the
+/// upper left map tile at the origin.
+pub struct Camera {
+    offset: Vector2<f32>,
+    zoom: f32,
+    follow: bool,
+    /// Distance the mouse moved since the button was pressed
+    drag: Option<f32>,
+}
+
+impl Camera {
+    pub fn new() -> Camera {
+        Camera {
+            offset: Vector2::zeros(),
+            zoom: 1.0,
+            follow: false,
+            drag: None,
+        }
+    }
+
+    /// Back to the whole map at its original scale
+    pub fn reset(&mut self) {
+        self.offset = Vector2::zeros();
+        self.zoom = 1.0;
+        self.follow = false;
+    }
+
+    #[inline(always)]
+    pub fn zoom(&self) -> f32 {
+        self.zoom
+    }
+
+    pub fn to_screen(&self, point: Point2<f32>) -> Point2<f32> {
+        point * self.zoom + self.offset
+    }
+
+    pub fn to_map(&self, point: Point2<f32>) -> Point2<f32> {
+        (point - self.offset) / self.zoom
+    }
+
+    /// Zooms by `steps` scroll wheel steps, keeping the map point under the
+    /// screen position `anchor` in place
+    pub fn zoom_by(&mut self, steps: f32, anchor: Point2<f32>) {
+        let map = self.to_map(anchor);
+        self.zoom = (self.zoom * ZOOM_STEP.powf(steps)).clamp(MIN_ZOOM, MAX_ZOOM);
+        self.offset = anchor - map * self.zoom;
+    }
+
+    // Following
+    #[inline(always)]
+    pub fn follow(&self) -> bool {
+        self.follow
+    }
+    #[inline(always)]
+    pub fn toggle_follow(&mut self) {
+        self.follow = !self.follow;
+    }
+    #[inline(always)]
+    pub fn stop_follow(&mut self) {
+        self.follow = false;
+    }
+
+    /// Centers the map point `target` on a screen of `screen_size` if
+    /// following
+    pub fn center_on(&mut self, target: Point2<f32>, screen_size: (f32, f32)) {
+        if self.follow {
+            self.offset =
+                Vector2::new(screen_size.0 / 2.0, screen_size.1 / 2.0) - target.coords * self.zoom;
+        }
+    }
+
+    // Dragging
+    /// Starts tracking a possible drag when the mouse button goes down
+    pub fn press(&mut self) {
+        self.drag = Some(0.0);
+    }
+
+    /// Pans the view by the mouse movement while the button is down
+    pub fn drag(&mut self, delta: Vector2<f32>) {
+        if let Some(distance) = &mut self.drag {
+            *distance += delta.norm();
+            if *distance >= DRAG_THRESHOLD {
+                self.offset += delta;
+                self.follow = false;
+            }
+        }
+    }
+
+    /// Stops tracking when the mouse button goes up, returns whether the
+    /// press was a click rather than a drag
+    pub fn release(&mut self) -> bool {
+        self.drag
+            .take()
+            .is_some_and(|distance| distance < DRAG_THRESHOLD)
+    }
+
+    // Drawing
+    /// Draws everything until [`Camera::pop`] through the camera
+    pub fn push(&self, ctx: &mut Context) -> GameResult {
+        graphics::push_transform(
+            ctx,
+            Some(
+                DrawParam::default()
+                    .dest(Point2::from(self.offset))
+                    .scale(Vector2::new(self.zoom, self.zoom))
+                    .to_matrix(),
+            ),
+        );
+        graphics::apply_transformations(ctx)
+    }
+
+    pub fn pop(&self, ctx: &mut Context) -> GameResult {
+        graphics::pop_transform(ctx);
+        graphics::apply_transformations(ctx)
+    }
+}
+
+impl Default for Camera {
+    fn default() -> Camera {
+        Camera::new()
+    }
+}
@@ -0,0 +1,130 @@
+//! blob-sim debug overlay
+//!
+
+// ============================================================================
+// Imports
+// ============================================================================
+
+use super::super::blobs::Blob;
+use super::{sprites, TEXT_COLOR};
+use ggez::{
+    graphics::{self, Color, DrawMode, DrawParam, FilterMode, MeshBuilder},
+    nalgebra::{Point2, Translation2},
+    Context, GameResult,
+};
+use std::collections::BTreeSet;
+
+// ============================================================================
+// Constants
+// ============================================================================
+
+pub const SENSE_COLOR: Color = Color {
+    r: 1.0,
+    g: 1.0,
+    b: 1.0,
+    a: 0.35,
+};
+
+// ============================================================================
+// Debug Overlay
+// ============================================================================
+
+/// What a blob is up to: its sense radius, the way to its destination, its
+/// state and its remaining energy
+///
+/// Shown for every blob or only for the blobs toggled individually.
+pub struct DebugOverlay {
+    all: bool,
+    /// Ids of the individually toggled blobs
+    blobs: BTreeSet<usize>,
+}
+
+impl DebugOverlay {
+    pub fn new() -> DebugOverlay {
+        DebugOverlay {
+            all: false,
+            blobs: BTreeSet::new(),
+        }
+    }
+
+    #[inline(always)]
+    pub fn all(&self) -> bool {
+        self.all
+    }
+    /// Shows or hides the overlay for every blob
+    #[inline(always)]
+    pub fn toggle_all(&mut self) {
+        self.all = !self.all;
+    }
+
+    /// Shows or hides the overlay for the blob with `id`
+    pub fn toggle(&mut self, id: usize) {
+        if !self.blobs.remove(&id) {
+            self.blobs.insert(id);
+        }
+    }
+
+    /// Whether the overlay is shown for `blob`
+    pub fn shows(&self, blob: &Blob) -> bool {
+        self.all || self.blobs.contains(&blob.id())
+    }
+
+    /// Forgets all individually toggled blobs
+    pub fn clear(&mut self) {
+        self.blobs.clear();
+    }
+
+    pub fn draw(
+        &self,
+        ctx: &mut Context,
+        blobs: &[Blob],
+        world_offset: Translation2<f32>,
+        alpha: f32,
+    ) -> GameResult {
+        let shown: Vec<&Blob> = blobs.iter().filter(|b| self.shows(b)).collect();
+        if shown.is_empty() {
+            return Ok(());
+        }
+        let mut mesh = MeshBuilder::new();
+        for blob in &shown {
+            let center = sprites::center(world_offset, blob.position_at(alpha));
+            let color = sprites::state_color(blob.state());
+            mesh.circle(
+                DrawMode::stroke(1.0),
+                center,
+                blob.sense(),
+                1.0,
+                SENSE_COLOR,
+            );
+            if blob.position() != blob.destination() {
+                mesh.line(
+                    &[center, sprites::center(world_offset, blob.destination())],
+                    1.0,
+                    color,
+                )?;
+            }
+        }
+        let mesh = mesh.build(ctx)?;
+        graphics::draw(ctx, &mesh, DrawParam::default())?;
+        for blob in &shown {
+            let center = sprites::center(world_offset, blob.position_at(alpha));
+            let label = graphics::Text::new(format!("{:?}\n{:.1}", blob.state(), blob.energy()));
+            graphics::queue_text(
+                ctx,
+                &label,
+                Point2::new(
+                    center.x + sprites::OUTLINE_RADIUS * blob.size(),
+                    center.y - super::LINE_HEIGHT,
+                ),
+                Some(TEXT_COLOR),
+            );
+        }
+        graphics::draw_queued_text(ctx, DrawParam::default(), None, FilterMode::Linear)
+    }
+}
+
+impl Default for DebugOverlay {
+    fn default() -> DebugOverlay {
+        DebugOverlay::new()
+    }
+}
@@ -0,0 +1,120 @@
+//! blob-sim trait distributions
+//!
+
+// ============================================================================
+// Imports
+// ============================================================================
+
+use super::super::{
+    blobs::Blob,
+    genome::{self, Genes},
+};
+use ggez::{graphics, Context, GameResult};
+
+// ============================================================================
+// Constants
+// ============================================================================
+
+/// Bars of each histogram
+pub const HISTOGRAM_BINS: usize = 16;
+/// Size of a single chart
+pub const CHART_SIZE: (f32, f32) = (200.0, 150.0);
+
+// ============================================================================
+// Distributions
+// ============================================================================
+
+/// Histograms of every trait and a scatter plot of two of them
+///
+/// The panel shows the population at the start of the current generation, it
+/// is updated once per generation.
+pub struct Distributions {
+    visible: bool,
+    /// Gene names
+    names: Vec<String>,
+    /// Gene values by gene
+    values: Vec<Vec<f32>>,
+    /// Traits on the axes of the scatter plot
+    scatter: (usize, usize),
+}
+
+impl Distributions {
+    pub fn new() -> Distributions {
+        Distributions {
+            visible: false,
+            names: vec![],
+            values: vec![],
+            // Speed vs. size
+            scatter: (genome::SPEED, genome::SIZE),
+        }
+    }
+
+    #[inline(always)]
+    pub fn visible(&self) -> bool {
+        self.visible
+    }
+    #[inline(always)]
+    pub fn toggle(&mut self) {
+        self.visible = !self.visible;
+    }
+
+    /// Takes the values of every gene of `blobs`
+    pub fn update(&mut self, genes: &Genes, blobs: &[Blob]) {
+        self.names = genes.iter().map(|gene| gene.name().to_owned()).collect();
+        self.values = (0..genes.len())
+            .map(|i| blobs.iter().map(|b| b.genome()[i]).collect())
+            .collect();
+        // The scatter plot may still show genes of the run before a snapshot
+        // was loaded
+        self.scatter = (self.scatter.0 % genes.len(), self.scatter.1 % genes.len());
+    }
+
+    /// Shows the next trait on the x axis of the scatter plot
+    pub fn cycle_x(&mut self) {
+        self.scatter.0 = (self.scatter.0 + 1) % self.names.len().max(1);
+    }
+
+    /// Shows the next trait on the y axis of the scatter plot
+    pub fn cycle_y(&mut self) {
+        self.scatter.1 = (self.scatter.1 + 1) % self.names.len().max(1);
+    }
+
+    /// Draws the charts in a grid in the lower left corner
+    pub fn draw(&self, ctx: &mut Context) -> GameResult {
+        if !self.visible || self.names.is_empty() {
+            return Ok(());
+        }
+        let screen = graphics::screen_coordinates(ctx);
+        let columns = 2;
+        let rows = (self.names.len() + 1).div_ceil(columns);
+        let chart = |i: usize| {
+            graphics::Rect::new(
+                super::MARGIN + (i % columns) as f32 * (CHART_SIZE.0 + super::MARGIN),
+                screen.bottom() - (rows - i / columns) as f32 * (CHART_SIZE.1 + super::MARGIN),
+                CHART_SIZE.0,
+                CHART_SIZE.1,
+            )
+        };
+        for (i, (name, values)) in self.names.iter().zip(&self.values).enumerate() {
+            super::draw_histogram(ctx, chart(i), name, values, HISTOGRAM_BINS)?;
+        }
+        let (x, y) = self.scatter;
+        let points: Vec<(f32, f32)> = self.values[x]
+            .iter()
+            .copied()
+            .zip(self.values[y].iter().copied())
+            .collect();
+        super::draw_scatter(
+            ctx,
+            chart(self.names.len()),
+            (&self.names[x], &self.names[y]),
+            &points,
+        )
+    }
+}
+
+impl Default for Distributions {
+    fn default() -> Distributions {
+        Distributions::new()
+    }
+}
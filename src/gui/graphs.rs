@@ -0,0 +1,162 @@
+//! blob-sim trait evolution graphs
+//!
+
+// ============================================================================
+// Imports
+// ============================================================================
+
+use super::super::stats::GenerationStats;
+use ggez::{graphics, Context, GameResult};
+use std::collections::VecDeque;
+
+// ============================================================================
+// Constants
+// ============================================================================
+
+/// Generations shown by the graphs, older ones scroll out to the left
+pub const GRAPH_GENERATIONS: usize = 100;
+/// Width of the side panel
+pub const GRAPH_WIDTH: f32 = 280.0;
+
+// ============================================================================
+// Graphs
+// ============================================================================
+
+/// Population, population per species and trait means per generation as
+/// line charts in a side panel
+pub struct Graphs {
+    visible: bool,
+    /// The last [`GRAPH_GENERATIONS`] generations
+    history: VecDeque<GenerationStats>,
+}
+
+impl Graphs {
+    pub fn new() -> Graphs {
+        Graphs {
+            visible: false,
+            history: VecDeque::with_capacity(GRAPH_GENERATIONS),
+        }
+    }
+
+    #[inline(always)]
+    pub fn visible(&self) -> bool {
+        self.visible
+    }
+    #[inline(always)]
+    pub fn toggle(&mut self) {
+        self.visible = !self.visible;
+    }
+
+    /// Statistics of the last [`GRAPH_GENERATIONS`] generations since the
+    /// last reset, oldest first
+    #[inline(always)]
+    pub fn history(&self) -> &VecDeque<GenerationStats> {
+        &self.history
+    }
+
+    /// Adds the statistics of a generation, dropping the oldest once
+    /// [`GRAPH_GENERATIONS`] are kept
+    pub fn record(&mut self, stats: GenerationStats) {
+        if self.history.len() == GRAPH_GENERATIONS {
+            self.history.pop_front();
+        }
+        self.history.push_back(stats);
+    }
+
+    pub fn clear(&mut self) {
+        self.history.clear();
+    }
+
+    pub fn draw(&self, ctx: &mut Context) -> GameResult {
+        if !self.visible {
+            return Ok(());
+        }
+        let mut series = vec![(
+            "population".to_owned(),
+            self.history
+                .iter()
+                .map(|s| s.population as f32)
+                .collect::<Vec<f32>>(),
+        )];
+        let species = self.history.back().map_or(vec![], |stats| {
+            stats.species.iter().map(|s| s.name.as_str()).collect()
+        });
+        for name in species {
+            series.push((
+                format!("{} population", name),
+                self.history
+                    .iter()
+                    .map(|s| {
+                        s.species
+                            .iter()
+                            .find(|species| species.name == name)
+                            .map_or(f32::NAN, |species| species.population as f32)
+                    })
+                    .collect(),
+            ));
+        }
+        let names = self.history.back().map_or(vec![], |stats| {
+            stats.traits.iter().map(|t| t.name.as_str()).collect()
+        });
+        for name in names {
+            series.push((
+                name.to_owned(),
+                self.history
+                    .iter()
+                    .map(|s| s.trait_stats(name).map_or(f32::NAN, |t| t.mean))
+                    .collect(),
+            ));
+        }
+        let screen = graphics::screen_coordinates(ctx);
+        let height = (screen.h - 2.0 * super::MARGIN - (series.len() - 1) as f32 * super::MARGIN)
+            / series.len() as f32;
+        for (i, (name, values)) in series.iter().enumerate() {
+            super::draw_line_chart(
+                ctx,
+                graphics::Rect::new(
+                    screen.right() - GRAPH_WIDTH - super::MARGIN,
+                    super::MARGIN + i as f32 * (height + super::MARGIN),
+                    GRAPH_WIDTH,
+                    height,
+                ),
+                name,
+                values,
+            )?;
+        }
+        Ok(())
+    }
+}
+
+impl Default for Graphs {
+    fn default() -> Graphs {
+        Graphs::new()
+    }
+}
+
+// ============================================================================
+// Testing
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::settings::Settings;
+
+    fn stats(generation: u32) -> GenerationStats {
+        let genes = Settings::default().genes().clone();
+        GenerationStats::new(0, generation, &[], &genes, 0, (0, 0), (0, 0))
+    }
+
+    #[test]
+    fn history_keeps_the_last_generations() {
+        let mut graphs = Graphs::new();
+        for generation in 0..250 {
+            graphs.record(stats(generation));
+        }
+        assert_eq!(graphs.history().len(), GRAPH_GENERATIONS);
+        assert_eq!(graphs.history().front().unwrap().generation, 150);
+        assert_eq!(graphs.history().back().unwrap().generation, 249);
+        graphs.clear();
+        assert!(graphs.history().is_empty());
+    }
+}
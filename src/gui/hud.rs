@@ -0,0 +1,96 @@
+//! blob-sim heads-up display
+//!
+
+// ============================================================================
+// Imports
+// ============================================================================
+
+use super::super::{blobs::Blob, clock::Clock, simulation::SimulationCore, stats, SimulationState};
+use ggez::{graphics, nalgebra::Point2, Context, GameResult};
+
+// ============================================================================
+// Hud
+// ============================================================================
+
+/// Live numbers of the running simulation in the upper left corner
+pub struct Hud {
+    visible: bool,
+}
+
+impl Hud {
+    pub fn new() -> Hud {
+        Hud { visible: true }
+    }
+
+    #[inline(always)]
+    pub fn visible(&self) -> bool {
+        self.visible
+    }
+    #[inline(always)]
+    pub fn toggle(&mut self) {
+        self.visible = !self.visible;
+    }
+
+    pub fn draw(
+        &self,
+        ctx: &mut Context,
+        core: &SimulationCore,
+        state: SimulationState,
+        clock: &Clock,
+    ) -> GameResult {
+        if !self.visible {
+            return Ok(());
+        }
+        let mut lines = vec![
+            format!("{:?} at {} | seed {}", state, clock, core.seed()),
+            format!(
+                "generation {} | {} frames left",
+                core.generation(),
+                core.generation_length()
+                    .saturating_sub(core.generation_frames())
+            ),
+            format!(
+                "blobs {} | food {}/{}",
+                core.blobs().len(),
+                core.food().len(),
+                core.food_placed()
+            ),
+        ];
+        for (i, gene) in core.settings().genes().iter().enumerate() {
+            let stats =
+                stats::TraitStats::new(gene.name(), core.blobs().iter().map(|b| b.genome()[i]));
+            lines.push(format!("{} {:.3}", gene.name(), stats.mean));
+        }
+        for (s, species) in core.settings().species().iter().enumerate() {
+            let blobs: Vec<&Blob> = core.blobs().iter().filter(|b| b.species() == s).collect();
+            let means: Vec<String> = core
+                .settings()
+                .genes()
+                .iter()
+                .enumerate()
+                .map(|(i, gene)| {
+                    let stats =
+                        stats::TraitStats::new(gene.name(), blobs.iter().map(|b| b.genome()[i]));
+                    format!("{} {:.3}", gene.name(), stats.mean)
+                })
+                .collect();
+            lines.push(format!(
+                "{} {} | {}",
+                species.name(),
+                blobs.len(),
+                means.join(" ")
+            ));
+        }
+        super::draw_text_panel(
+            ctx,
+            &graphics::Text::new(lines.join("\n")),
+            Point2::new(super::MARGIN, super::MARGIN),
+        )
+    }
+}
+
+impl Default for Hud {
+    fn default() -> Hud {
+        Hud::new()
+    }
+}
@@ -0,0 +1,132 @@
+//! blob-sim blob inspector
+//!
+
+// ============================================================================
+// Imports
+// ============================================================================
+
+use super::super::simulation::SimulationCore;
+use super::sprites;
+use ggez::{
+    graphics::{self, DrawMode, DrawParam},
+    nalgebra::{Point2, Translation2},
+    Context, GameResult,
+};
+
+// ============================================================================
+// Inspector
+// ============================================================================
+
+/// Details of the selected blob
+///
+/// The selection is kept by id, so it follows the blob from frame to frame
+/// until the blob dies.
+pub struct Inspector {
+    selected: Option<usize>,
+}
+
+impl Inspector {
+    pub fn new() -> Inspector {
+        Inspector { selected: None }
+    }
+
+    /// Id of the selected blob
+    #[inline(always)]
+    pub fn selected(&self) -> Option<usize> {
+        self.selected
+    }
+    #[inline(always)]
+    pub fn select(&mut self, id: Option<usize>) {
+        self.selected = id;
+    }
+
+    /// Clears the selection if the selected blob is no longer alive
+    pub fn update(&mut self, core: &SimulationCore) {
+        if self.selected.is_some_and(|id| core.blob(id).is_none()) {
+            self.selected = None;
+        }
+    }
+
+    /// Circles the selected blob in the world
+    pub fn draw_highlight(
+        &self,
+        ctx: &mut Context,
+        core: &SimulationCore,
+        world_offset: Translation2<f32>,
+        alpha: f32,
+    ) -> GameResult {
+        let blob = match self.selected.and_then(|id| core.blob(id)) {
+            Some(blob) => blob,
+            None => return Ok(()),
+        };
+        let highlight = graphics::Mesh::new_circle(
+            ctx,
+            DrawMode::stroke(2.0),
+            sprites::center(world_offset, blob.position_at(alpha)),
+            sprites::OUTLINE_RADIUS * blob.size() + 4.0,
+            0.5,
+            graphics::WHITE,
+        )?;
+        graphics::draw(ctx, &highlight, DrawParam::default())
+    }
+
+    /// Shows the details of the selected blob with the right edge of the
+    /// panel at `right`
+    pub fn draw(&self, ctx: &mut Context, core: &SimulationCore, right: f32) -> GameResult {
+        let blob = match self.selected.and_then(|id| core.blob(id)) {
+            Some(blob) => blob,
+            None => return Ok(()),
+        };
+        let mut lines = vec![
+            match core.settings().species().get(blob.species()) {
+                Some(species) => format!("blob {} | {}", blob.id(), species.name()),
+                None => format!("blob {}", blob.id()),
+            },
+            format!(
+                "born in generation {} | parents {}",
+                blob.born(),
+                if blob.parents().is_empty() {
+                    "-".to_owned()
+                } else {
+                    blob.parents()
+                        .iter()
+                        .map(|p| p.to_string())
+                        .collect::<Vec<String>>()
+                        .join(", ")
+                }
+            ),
+        ];
+        for (gene, value) in core.settings().genes().iter().zip(blob.genome().values()) {
+            lines.push(format!("{} {:.3}", gene.name(), value));
+        }
+        lines.extend(vec![
+            format!("energy {:.1}", blob.energy()),
+            format!("food found {}", blob.food_found()),
+            format!("state {:?}", blob.state()),
+            format!(
+                "mate {}",
+                blob.mate()
+                    .map_or_else(|| "-".to_owned(), |m| m.id.to_string())
+            ),
+            format!(
+                "position {:.0}, {:.0}",
+                blob.position().x,
+                blob.position().y
+            ),
+            format!(
+                "destination {:.0}, {:.0}",
+                blob.destination().x,
+                blob.destination().y
+            ),
+        ]);
+        let text = graphics::Text::new(lines.join("\n"));
+        let width = text.width(ctx) as f32 + 2.0 * super::PADDING;
+        super::draw_text_panel(ctx, &text, Point2::new(right - width, super::MARGIN))
+    }
+}
+
+impl Default for Inspector {
+    fn default() -> Inspector {
+        Inspector::new()
+    }
+}
@@ -0,0 +1,224 @@
+//! blob-sim gui module
+//!
+//! Overlays drawn by the [`Simulation`](super::Simulation) frontend on top of
+//! the world.
+
+// ============================================================================
+// Modules
+// ============================================================================
+
+pub mod camera;
+pub mod debug;
+pub mod distributions;
+pub mod graphs;
+pub mod hud;
+pub mod inspector;
+pub mod sprites;
+
+// ============================================================================
+// Imports
+// ============================================================================
+
+use ggez::{
+    graphics::{self, DrawMode, DrawParam, MeshBuilder, Rect},
+    nalgebra::Point2,
+    Context, GameResult,
+};
+
+// ============================================================================
+// Constants
+// ============================================================================
+
+/// Margin between overlays and the window border
+pub const MARGIN: f32 = 8.0;
+/// Padding between an overlay's background and its content
+pub const PADDING: f32 = 6.0;
+/// Height of a line of text in the default font
+pub const LINE_HEIGHT: f32 = 16.0;
+
+pub const PANEL_COLOR: graphics::Color = graphics::Color {
+    r: 0.0,
+    g: 0.0,
+    b: 0.0,
+    a: 0.6,
+};
+pub const TEXT_COLOR: graphics::Color = graphics::WHITE;
+pub const LINE_COLOR: graphics::Color = graphics::Color {
+    r: 1.0,
+    g: 0.8,
+    b: 0.2,
+    a: 1.0,
+};
+
+// ============================================================================
+// Helpers
+// ============================================================================
+
+/// Draws `text` on a translucent panel with its upper left corner at `dest`
+pub fn draw_text_panel(ctx: &mut Context, text: &graphics::Text, dest: Point2<f32>) -> GameResult {
+    let (width, height) = text.dimensions(ctx);
+    let panel = graphics::Mesh::new_rectangle(
+        ctx,
+        DrawMode::fill(),
+        Rect::new(
+            dest.x,
+            dest.y,
+            width as f32 + 2.0 * PADDING,
+            height as f32 + 2.0 * PADDING,
+        ),
+        PANEL_COLOR,
+    )?;
+    graphics::draw(ctx, &panel, DrawParam::default())?;
+    graphics::draw(
+        ctx,
+        text,
+        DrawParam::default()
+            .dest(Point2::new(dest.x + PADDING, dest.y + PADDING))
+            .color(TEXT_COLOR),
+    )
+}
+
+/// Draws `values` as a line chart filling `bounds`, scaled to their range
+///
+/// Non finite values, e.g. trait means of an extinct population, leave gaps.
+pub fn draw_line_chart(ctx: &mut Context, bounds: Rect, title: &str, values: &[f32]) -> GameResult {
+    let mut mesh = MeshBuilder::new();
+    mesh.rectangle(DrawMode::fill(), bounds, PANEL_COLOR);
+    let (min, max) = value_range(values.iter().copied());
+    let plot = plot_area(bounds);
+    let step = plot.w / (values.len().max(2) - 1) as f32;
+    let points: Vec<Option<Point2<f32>>> = values
+        .iter()
+        .enumerate()
+        .map(|(i, &v)| {
+            if v.is_finite() {
+                Some(Point2::new(
+                    plot.x + i as f32 * step,
+                    plot.bottom() - (v - min) / (max - min) * plot.h,
+                ))
+            } else {
+                None
+            }
+        })
+        .collect();
+    for segment in points.split(Option::is_none) {
+        let segment: Vec<Point2<f32>> = segment.iter().flatten().copied().collect();
+        if segment.len() >= 2 {
+            mesh.line(&segment, 1.5, LINE_COLOR)?;
+        }
+    }
+    let mesh = mesh.build(ctx)?;
+    graphics::draw(ctx, &mesh, DrawParam::default())?;
+    let label = match values.last() {
+        Some(last) => format!("{} {:.3}  [{:.3}, {:.3}]", title, last, min, max),
+        None => title.to_owned(),
+    };
+    draw_label(ctx, bounds, &label)
+}
+
+/// Draws a histogram of `values` with `bins` bars filling `bounds`
+pub fn draw_histogram(
+    ctx: &mut Context,
+    bounds: Rect,
+    title: &str,
+    values: &[f32],
+    bins: usize,
+) -> GameResult {
+    let mut mesh = MeshBuilder::new();
+    mesh.rectangle(DrawMode::fill(), bounds, PANEL_COLOR);
+    let (min, max) = value_range(values.iter().copied());
+    let mut counts = vec![0usize; bins];
+    for v in values.iter().filter(|v| v.is_finite()) {
+        let bin = ((v - min) / (max - min) * bins as f32) as usize;
+        counts[bin.min(bins - 1)] += 1;
+    }
+    let plot = plot_area(bounds);
+    let highest = counts.iter().copied().max().unwrap_or(0).max(1) as f32;
+    let width = plot.w / bins as f32;
+    for (i, &count) in counts.iter().enumerate().filter(|(_, &c)| c > 0) {
+        let height = count as f32 / highest * plot.h;
+        mesh.rectangle(
+            DrawMode::fill(),
+            Rect::new(
+                plot.x + i as f32 * width,
+                plot.bottom() - height,
+                (width - 1.0).max(1.0),
+                height,
+            ),
+            LINE_COLOR,
+        );
+    }
+    let mesh = mesh.build(ctx)?;
+    graphics::draw(ctx, &mesh, DrawParam::default())?;
+    draw_label(ctx, bounds, &format!("{} [{:.3}, {:.3}]", title, min, max))
+}
+
+/// Draws `points` as a scatter plot filling `bounds`, scaled to their range
+pub fn draw_scatter(
+    ctx: &mut Context,
+    bounds: Rect,
+    titles: (&str, &str),
+    points: &[(f32, f32)],
+) -> GameResult {
+    let mut mesh = MeshBuilder::new();
+    mesh.rectangle(DrawMode::fill(), bounds, PANEL_COLOR);
+    let x_range = value_range(points.iter().map(|p| p.0));
+    let y_range = value_range(points.iter().map(|p| p.1));
+    let plot = plot_area(bounds);
+    for &(x, y) in points
+        .iter()
+        .filter(|(x, y)| x.is_finite() && y.is_finite())
+    {
+        mesh.rectangle(
+            DrawMode::fill(),
+            Rect::new(
+                plot.x + (x - x_range.0) / (x_range.1 - x_range.0) * plot.w - 1.5,
+                plot.bottom() - (y - y_range.0) / (y_range.1 - y_range.0) * plot.h - 1.5,
+                3.0,
+                3.0,
+            ),
+            LINE_COLOR,
+        );
+    }
+    let mesh = mesh.build(ctx)?;
+    graphics::draw(ctx, &mesh, DrawParam::default())?;
+    draw_label(ctx, bounds, &format!("{} / {}", titles.0, titles.1))
+}
+
+/// Finite range of `values`, widened if it is empty or a single value
+pub fn value_range<I: Iterator<Item = f32>>(values: I) -> (f32, f32) {
+    let (min, max) = values
+        .filter(|v| v.is_finite())
+        .fold((f32::INFINITY, f32::NEG_INFINITY), |(min, max), v| {
+            (min.min(v), max.max(v))
+        });
+    if min > max {
+        (0.0, 1.0)
+    } else if min == max {
+        let margin = (min.abs() * 0.1).max(0.5);
+        (min - margin, max + margin)
+    } else {
+        (min, max)
+    }
+}
+
+/// Area of a chart below its label
+fn plot_area(bounds: Rect) -> Rect {
+    Rect::new(
+        bounds.x + PADDING,
+        bounds.y + PADDING + LINE_HEIGHT,
+        bounds.w - 2.0 * PADDING,
+        bounds.h - 2.0 * PADDING - LINE_HEIGHT,
+    )
+}
+
+/// Draws the label of a chart in its upper left corner
+fn draw_label(ctx: &mut Context, bounds: Rect, label: &str) -> GameResult {
+    graphics::draw(
+        ctx,
+        &graphics::Text::new(label),
+        DrawParam::default()
+            .dest(Point2::new(bounds.x + PADDING, bounds.y + PADDING))
+            .color(TEXT_COLOR),
+    )
+}
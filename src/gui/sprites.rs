@@ -0,0 +1,217 @@
+//! blob-sim blob sprites
+//!
+
+// ============================================================================
+// Imports
+// ============================================================================
+
+use super::super::{
+    blobs::{Blob, BlobState},
+    genome::Genes,
+    resources::Resources,
+    species::Species,
+    TILE_SIZE,
+};
+use ggez::{
+    graphics::{self, Color, DrawMode, DrawParam, MeshBuilder},
+    nalgebra::{distance, Point2, Translation2, Vector2},
+    Context, GameResult,
+};
+
+// ============================================================================
+// Constants
+// ============================================================================
+
+/// Stops of the color ramp, from the lowest to the highest trait value
+pub const RAMP: [Color; 3] = [
+    Color {
+        r: 0.3,
+        g: 0.5,
+        b: 1.0,
+        a: 1.0,
+    },
+    Color {
+        r: 0.4,
+        g: 1.0,
+        b: 0.4,
+        a: 1.0,
+    },
+    Color {
+        r: 1.0,
+        g: 0.35,
+        b: 0.25,
+        a: 1.0,
+    },
+];
+
+/// Outline radius of a blob of size 1
+pub const OUTLINE_RADIUS: f32 = TILE_SIZE * 0.3;
+
+// ============================================================================
+// Blob Style
+// ============================================================================
+
+/// How blobs are drawn
+///
+/// Sprites are scaled by the blob size and tinted by the position of a gene
+/// within the current population on [`RAMP`], or by the tint of their
+/// species while no gene is chosen, which is the default with species.
+/// Outlines show the [`BlobState`].
+pub struct BlobStyle {
+    /// Index of the gene the sprites are tinted by
+    tint: Option<usize>,
+    outline: bool,
+}
+
+impl BlobStyle {
+    pub fn new() -> BlobStyle {
+        BlobStyle {
+            tint: Some(0),
+            outline: false,
+        }
+    }
+
+    /// Tints by the species if there are any, by the first gene otherwise
+    pub fn reset_tint(&mut self, species: &[Species]) {
+        self.tint = if species.is_empty() { Some(0) } else { None };
+    }
+
+    /// Name of the gene the sprites are tinted by
+    pub fn tint<'a>(&self, genes: &'a Genes) -> Option<&'a str> {
+        self.tint.and_then(|i| genes.get(i)).map(|gene| gene.name())
+    }
+
+    /// Tints by the next gene, after the last one the sprites are untinted
+    pub fn cycle_tint(&mut self, genes: &Genes) {
+        self.tint = match self.tint {
+            Some(i) if i + 1 < genes.len() => Some(i + 1),
+            Some(_) => None,
+            None => Some(0),
+        };
+    }
+
+    #[inline(always)]
+    pub fn outline(&self) -> bool {
+        self.outline
+    }
+    #[inline(always)]
+    pub fn toggle_outline(&mut self) {
+        self.outline = !self.outline;
+    }
+
+    /// Draws `blobs` with the sprites of their `species`, `world_offset`
+    /// being the map position of the world origin and `alpha` the fraction of
+    /// the next tick to interpolate positions by
+    pub fn draw(
+        &self,
+        ctx: &mut Context,
+        res: &Resources,
+        species: &[Species],
+        blobs: &[Blob],
+        world_offset: Translation2<f32>,
+        alpha: f32,
+    ) -> GameResult {
+        let tint = self
+            .tint
+            .map(|i| (i, super::value_range(blobs.iter().map(|b| b.genome()[i]))));
+        for blob in blobs {
+            let scale = blob.size();
+            let color = match tint {
+                Some((i, (min, max))) => ramp((blob.genome()[i] - min) / (max - min)),
+                None => species
+                    .get(blob.species())
+                    .and_then(Species::tint)
+                    .map_or(graphics::WHITE, |(r, g, b)| Color::new(r, g, b, 1.0)),
+            };
+            // Keep the sprite centered on the same spot at any scale
+            let dest = world_offset.transform_point(&blob.position_at(alpha))
+                + Vector2::repeat(TILE_SIZE / 2.0 * (1.0 - scale));
+            graphics::draw(
+                ctx,
+                res.sprite(blob.species()),
+                DrawParam::default()
+                    .dest(dest)
+                    .scale(Vector2::new(scale, scale))
+                    .color(color),
+            )?;
+        }
+        if self.outline && !blobs.is_empty() {
+            let mut mesh = MeshBuilder::new();
+            for blob in blobs {
+                mesh.circle(
+                    DrawMode::stroke(2.0),
+                    center(world_offset, blob.position_at(alpha)),
+                    OUTLINE_RADIUS * blob.size(),
+                    0.5,
+                    state_color(blob.state()),
+                );
+            }
+            let mesh = mesh.build(ctx)?;
+            graphics::draw(ctx, &mesh, DrawParam::default())?;
+        }
+        Ok(())
+    }
+}
+
+impl Default for BlobStyle {
+    fn default() -> BlobStyle {
+        BlobStyle::new()
+    }
+}
+
+// ============================================================================
+// Helpers
+// ============================================================================
+
+/// Map position of the center of a sprite at `position`
+pub fn center(world_offset: Translation2<f32>, position: Point2<f32>) -> Point2<f32> {
+    world_offset.transform_point(&position) + Vector2::repeat(TILE_SIZE / 2.0)
+}
+
+/// Blob drawn at the map position `point`, the closest one if several are
+pub fn blob_at(
+    blobs: &[Blob],
+    world_offset: Translation2<f32>,
+    alpha: f32,
+    point: Point2<f32>,
+) -> Option<&Blob> {
+    blobs
+        .iter()
+        .map(|blob| {
+            let d = distance(&center(world_offset, blob.position_at(alpha)), &point);
+            (blob, d)
+        })
+        .filter(|(blob, d)| *d <= OUTLINE_RADIUS * blob.size())
+        .min_by(|a, b| a.1.total_cmp(&b.1))
+        .map(|(blob, _)| blob)
+}
+
+/// Color of `t` in `0.0..=1.0` on [`RAMP`]
+pub fn ramp(t: f32) -> Color {
+    let t = if t.is_finite() {
+        t.clamp(0.0, 1.0)
+    } else {
+        0.5
+    };
+    let scaled = t * (RAMP.len() - 1) as f32;
+    let i = (scaled as usize).min(RAMP.len() - 2);
+    let (a, b, f) = (RAMP[i], RAMP[i + 1], scaled - i as f32);
+    Color::new(
+        a.r + (b.r - a.r) * f,
+        a.g + (b.g - a.g) * f,
+        a.b + (b.b - a.b) * f,
+        1.0,
+    )
+}
+
+pub fn state_color(state: BlobState) -> Color {
+    match state {
+        BlobState::SearchFood => Color::new(1.0, 0.9, 0.2, 1.0),
+        BlobState::GoToFood => Color::new(0.2, 0.9, 0.3, 1.0),
+        BlobState::Hunt => Color::new(1.0, 0.2, 0.2, 1.0),
+        BlobState::Flee => Color::new(0.9, 0.3, 1.0, 1.0),
+        BlobState::SeekMate => Color::new(1.0, 0.5, 0.8, 1.0),
+        BlobState::GoHome => Color::new(0.2, 0.5, 1.0, 1.0),
+        BlobState::AtHome => Color::new(0.6, 0.6, 0.6, 1.0),
+    }
+}
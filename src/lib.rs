@@ -8,23 +8,46 @@
 // Modules
 // ============================================================================
 
+pub mod batch;
 pub mod blobs;
+pub mod cli;
+pub mod clock;
+pub mod food;
+pub mod genome;
+pub mod grid;
+pub mod gui;
+pub mod lineage;
 pub mod resources;
+pub mod selection;
 pub mod settings;
+pub mod simulation;
+pub mod snapshot;
+pub mod species;
+pub mod stats;
 
 // ============================================================================
 // Imports
 // ============================================================================
 
 use blobs::Blob;
+use clock::Clock;
 use ggez::{
     event, graphics, input,
-    nalgebra::{Point2, Translation2},
+    nalgebra::{Point2, Translation2, Vector2},
     timer, Context, GameResult,
 };
+use gui::{
+    camera::Camera,
+    debug::DebugOverlay,
+    distributions::Distributions,
+    graphs::{self, Graphs},
+    hud::Hud,
+    inspector::Inspector,
+    sprites::{self, BlobStyle},
+};
 use resources::Resources;
-use settings::Settings;
-use std::sync::{Arc, RwLock};
+use simulation::SimulationCore;
+use std::{path::PathBuf, time::Instant};
 
 // ============================================================================
 // Constants
@@ -36,50 +59,143 @@ pub const TILE_SIZE: f32 = 64.0;
 // Simulation
 // ============================================================================
 
+/// ggez frontend drawing and driving a [`SimulationCore`]
 pub struct Simulation {
     // State
     state: SimulationState,
-    blobs: Vec<Blob>,
-    food: Vec<Point2<f32>>,
-    generation_frames: u32,
+    clock: Clock,
+    core: SimulationCore,
+    snapshot: PathBuf,
+    // View
+    camera: Camera,
+    // Overlays
+    style: BlobStyle,
+    debug: DebugOverlay,
+    inspector: Inspector,
+    hud: Hud,
+    graphs: Graphs,
+    distributions: Distributions,
     // Resources
     res: Resources,
-    // Settings
-    settings: Arc<RwLock<Settings>>,
 }
 
 impl Simulation {
-    pub fn new(ctx: &mut Context) -> Simulation {
+    /// Creates a frontend for `core`, saving and loading snapshots at
+    /// `snapshot`
+    pub fn new(ctx: &mut Context, core: SimulationCore, snapshot: PathBuf) -> Simulation {
+        let mut distributions = Distributions::new();
+        distributions.update(core.settings().genes(), core.blobs());
+        let mut res = Resources::new(ctx);
+        res.load_species(ctx, core.settings().species());
+        let mut style = BlobStyle::new();
+        style.reset_tint(core.settings().species());
         Simulation {
             state: SimulationState::Stopped,
-            blobs: vec![],
-            food: vec![],
-            generation_frames: 0,
-            res: Resources::new(ctx),
-            settings: Arc::new(RwLock::new(Settings::default())),
+            clock: Clock::new(),
+            core,
+            snapshot,
+            camera: Camera::new(),
+            style,
+            debug: DebugOverlay::new(),
+            inspector: Inspector::new(),
+            hud: Hud::new(),
+            graphs: Graphs::new(),
+            distributions,
+            res,
         }
     }
 
     pub fn reset(&mut self, blobs: bool) {
-        self.food = vec![];
-        self.generation_frames = 0;
+        self.core.reset(blobs);
         if blobs {
-            self.blobs = vec![];
-            for _ in 0..self.settings.read().unwrap().start_blobs() {
-                self.blobs.push(Blob::new(self.settings.clone()))
-            }
+            self.graphs.clear();
+            self.debug.clear();
+            self.inspector.select(None);
         }
-        for _ in 0..self.settings.read().unwrap().food_per_gen() {
-            self.food.push(Point2::new(
-                rand::random::<f32>() * self.settings.read().unwrap().world_size().0,
-                rand::random::<f32>() * self.settings.read().unwrap().world_size().1,
-            ))
+        self.distributions
+            .update(self.core.settings().genes(), self.core.blobs());
+    }
+
+    /// Advances the simulation by a single tick
+    ///
+    /// A selected blob that died in the tick is deselected right away, which
+    /// also stops following it.
+    pub fn step(&mut self) {
+        if let Some(stats) = self.core.step() {
+            println!("{}", stats);
+            self.graphs.record(stats);
+            self.distributions
+                .update(self.core.settings().genes(), self.core.blobs());
         }
-        self.settings.write().unwrap().decay_food();
-        if blobs {
-            self.settings.write().unwrap().reset_food();
+        let selected = self.inspector.selected();
+        self.inspector.update(&self.core);
+        if selected.is_some() && self.inspector.selected().is_none() {
+            self.camera.stop_follow();
+        }
+    }
+
+    /// Advances the simulation to the start of the next generation
+    pub fn step_generation(&mut self) {
+        let generation = self.core.generation();
+        while self.core.generation() == generation {
+            self.step();
+        }
+    }
+
+    pub fn save_snapshot(&self) {
+        match snapshot::save(&self.core, &self.snapshot) {
+            Ok(()) => println!("snapshot saved to {}", self.snapshot.display()),
+            Err(e) => eprintln!("{}: {}", self.snapshot.display(), e),
+        }
+    }
+
+    pub fn load_snapshot(&mut self, ctx: &mut Context) {
+        match snapshot::load(&self.snapshot) {
+            Ok(core) => {
+                self.core = core;
+                self.res.load_species(ctx, self.core.settings().species());
+                self.style.reset_tint(self.core.settings().species());
+                self.graphs.clear();
+                self.debug.clear();
+                self.inspector.select(None);
+                self.distributions
+                    .update(self.core.settings().genes(), self.core.blobs());
+                self.state = SimulationState::Stopped;
+                println!("snapshot loaded from {}", self.snapshot.display());
+            }
+            Err(e) => eprintln!("{}: {}", self.snapshot.display(), e),
+        }
+    }
+
+    #[inline(always)]
+    pub fn core(&self) -> &SimulationCore {
+        &self.core
+    }
+
+    /// Map position of the world origin
+    #[inline(always)]
+    pub fn world_offset(&self) -> Translation2<f32> {
+        Translation2::new(TILE_SIZE / 2.0, TILE_SIZE / 2.0)
+    }
+
+    /// Fraction of the next tick to draw blobs at
+    fn alpha(&self) -> f32 {
+        match self.state {
+            SimulationState::Stopped => 1.0,
+            SimulationState::Running => self.clock.alpha(),
         }
     }
+
+    /// Id of the blob under the screen position `point`
+    fn blob_at(&self, point: Point2<f32>) -> Option<usize> {
+        sprites::blob_at(
+            self.core.blobs(),
+            self.world_offset(),
+            self.alpha(),
+            self.camera.to_map(point),
+        )
+        .map(Blob::id)
+    }
 }
 
 // ============================================================================
@@ -88,88 +204,79 @@ impl Simulation {
 
 impl event::EventHandler for Simulation {
     fn update(&mut self, ctx: &mut Context) -> GameResult {
-        while timer::check_update_time(ctx, self.settings.read().unwrap().fps()) {
-            match self.state {
-                SimulationState::Stopped => {
-                    // Do nothing
-                }
-                SimulationState::Running => {
-                    self.generation_frames = self.generation_frames + 1;
-                    if self.generation_frames
-                        > self.settings.read().unwrap().fps()
-                            * self.settings.read().unwrap().gen_duration()
-                    {
-                        let mut new_blobs = vec![];
-                        let mut dead_blobs = vec![];
-                        for blob in &mut self.blobs {
-                            match blob.next_gen() {
-                                blobs::GenerationResult::Starve => dead_blobs.push(blob.clone()),
-                                blobs::GenerationResult::Reproduce => {
-                                    new_blobs.push(Blob::evolve(blob))
-                                }
-                                blobs::GenerationResult::Live => {
-                                    // Nothing happens
-                                }
-                            }
-                        }
-                        self.blobs.retain(|b| !dead_blobs.contains(b));
-                        self.blobs.append(&mut new_blobs);
-                        self.reset(false);
-                        self.generation_frames = 0;
-                        let mut avg = (0.0, 0.0, 0.0);
-                        for blob in &self.blobs {
-                            avg.0 = avg.0 + blob.speed();
-                            avg.1 = avg.1 + blob.sense();
-                            avg.2 = avg.2 + blob.size();
-                        }
-                        avg.0 = avg.0 / self.blobs.len() as f32;
-                        avg.1 = avg.1 / self.blobs.len() as f32;
-                        avg.2 = avg.2 / self.blobs.len() as f32;
-                        println!(
-                            "speed: {}, sense: {}, size: {}, blobs: {}, food; {}",
-                            avg.0,
-                            avg.1,
-                            avg.2,
-                            self.blobs.len(),
-                            self.food.len()
-                        );
-                    } else {
-                        for blob in &mut self.blobs {
-                            blob.update(&mut self.food);
-                        }
-                    }
-                }
+        match self.state {
+            SimulationState::Stopped => {
+                // Don't catch up on the time spent stopped
+                self.clock.reset();
+            }
+            SimulationState::Running => {
+                let ticks = self
+                    .clock
+                    .ticks(timer::delta(ctx), self.core.settings().tick_rate());
+                let start = Instant::now();
+                let ran = clock::run_within_budget(ticks, || start.elapsed(), || self.step());
+                self.clock.ran(ran);
             }
         }
         Ok(())
     }
 
     fn draw(&mut self, ctx: &mut Context) -> GameResult {
-        let world_offset = Translation2::new(TILE_SIZE / 2.0, TILE_SIZE / 2.0);
+        let world_offset = self.world_offset();
+        let alpha = self.alpha();
+        let screen = graphics::screen_coordinates(ctx);
         graphics::clear(ctx, graphics::WHITE);
+        if let Some(blob) = self.inspector.selected().and_then(|id| self.core.blob(id)) {
+            self.camera.center_on(
+                sprites::center(world_offset, blob.position_at(alpha)),
+                (screen.w, screen.h),
+            );
+        }
+        self.camera.push(ctx)?;
         // Draw World
+        let map_size = self.core.settings().map_size();
         self.res.draw_map(
             ctx,
-            (self.settings.read().unwrap().screen_size().0 / TILE_SIZE).ceil() as usize,
-            (self.settings.read().unwrap().screen_size().1 / TILE_SIZE).ceil() as usize,
+            (map_size.0 / TILE_SIZE).ceil() as usize,
+            (map_size.1 / TILE_SIZE).ceil() as usize,
         );
         // Draw Food
-        for food in &self.food {
+        for (_, food) in self.core.food().iter() {
             graphics::draw(
                 ctx,
                 self.res.food(),
-                graphics::DrawParam::default().dest(world_offset.transform_point(food)),
+                graphics::DrawParam::default().dest(world_offset.transform_point(&food)),
             )?;
         }
         // Draw Blobs
-        for blob in &self.blobs {
-            graphics::draw(
-                ctx,
-                self.res.blob(),
-                graphics::DrawParam::default().dest(world_offset.transform_point(&blob.position())),
-            )?;
-        }
-        graphics::present(ctx)
+        self.style.draw(
+            ctx,
+            &self.res,
+            self.core.settings().species(),
+            self.core.blobs(),
+            world_offset,
+            alpha,
+        )?;
+        self.debug
+            .draw(ctx, self.core.blobs(), world_offset, alpha)?;
+        self.inspector
+            .draw_highlight(ctx, &self.core, world_offset, alpha)?;
+        self.camera.pop(ctx)?;
+        // Draw Overlays
+        self.hud.draw(ctx, &self.core, self.state, &self.clock)?;
+        self.graphs.draw(ctx)?;
+        let inspector_right = screen.right()
+            - gui::MARGIN
+            - if self.graphs.visible() {
+                graphs::GRAPH_WIDTH + gui::MARGIN
+            } else {
+                0.0
+            };
+        self.inspector.draw(ctx, &self.core, inspector_right)?;
+        self.distributions.draw(ctx)?;
+        graphics::present(ctx)?;
+        self.clock.limit_frame_rate(self.core.settings().fps());
+        Ok(())
     }
 
     fn key_down_event(
@@ -179,7 +286,16 @@ impl event::EventHandler for Simulation {
         keymods: input::keyboard::KeyMods,
         repeat: bool,
     ) {
-        if input::keyboard::KeyMods::empty() == keymods && !repeat {
+        // Shift is needed for + on most layouts
+        let speed_key = matches!(
+            keycode,
+            event::KeyCode::Add
+                | event::KeyCode::Equals
+                | event::KeyCode::Subtract
+                | event::KeyCode::Minus
+        );
+        let plain = keymods.is_empty() || (keymods == input::keyboard::KeyMods::SHIFT && speed_key);
+        if plain && !repeat {
             match keycode {
                 event::KeyCode::Escape => event::quit(ctx),
                 event::KeyCode::Space => match self.state {
@@ -187,16 +303,88 @@ impl event::EventHandler for Simulation {
                     SimulationState::Stopped => self.state = SimulationState::Running,
                 },
                 event::KeyCode::R => self.reset(true),
+                event::KeyCode::Add | event::KeyCode::Equals => self.clock.faster(),
+                event::KeyCode::Subtract | event::KeyCode::Minus => self.clock.slower(),
+                event::KeyCode::U => self.clock.toggle_uncapped(),
+                event::KeyCode::Period if self.state == SimulationState::Stopped => self.step(),
+                event::KeyCode::N if self.state == SimulationState::Stopped => {
+                    self.step_generation()
+                }
+                event::KeyCode::B => self.debug.toggle_all(),
+                event::KeyCode::H => self.hud.toggle(),
+                event::KeyCode::G => self.graphs.toggle(),
+                event::KeyCode::C => self.style.cycle_tint(self.core.settings().genes()),
+                event::KeyCode::O => self.style.toggle_outline(),
+                event::KeyCode::D => self.distributions.toggle(),
+                event::KeyCode::X => self.distributions.cycle_x(),
+                event::KeyCode::Y => self.distributions.cycle_y(),
+                event::KeyCode::F => self.camera.toggle_follow(),
+                event::KeyCode::Home => self.camera.reset(),
+                event::KeyCode::F5 => self.save_snapshot(),
+                event::KeyCode::F9 => self.load_snapshot(ctx),
                 _ => {}
             }
         }
     }
+
+    fn mouse_button_down_event(
+        &mut self,
+        _ctx: &mut Context,
+        button: event::MouseButton,
+        x: f32,
+        y: f32,
+    ) {
+        match button {
+            event::MouseButton::Left => self.camera.press(),
+            event::MouseButton::Right => {
+                if let Some(id) = self.blob_at(Point2::new(x, y)) {
+                    self.debug.toggle(id);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    fn mouse_button_up_event(
+        &mut self,
+        _ctx: &mut Context,
+        button: event::MouseButton,
+        x: f32,
+        y: f32,
+    ) {
+        // Dragging pans, clicking selects
+        if button == event::MouseButton::Left && self.camera.release() {
+            let blob = self.blob_at(Point2::new(x, y));
+            self.inspector.select(blob);
+        }
+    }
+
+    fn mouse_motion_event(&mut self, ctx: &mut Context, _x: f32, _y: f32, dx: f32, dy: f32) {
+        if input::mouse::button_pressed(ctx, event::MouseButton::Left) {
+            self.camera.drag(Vector2::new(dx, dy));
+        }
+    }
+
+    fn mouse_wheel_event(&mut self, ctx: &mut Context, _x: f32, y: f32) {
+        self.camera
+            .zoom_by(y, Point2::from(input::mouse::position(ctx)));
+    }
+
+    fn resize_event(&mut self, ctx: &mut Context, width: f32, height: f32) {
+        // Keep one pixel per pixel instead of stretching the view
+        if let Err(e) =
+            graphics::set_screen_coordinates(ctx, graphics::Rect::new(0.0, 0.0, width, height))
+        {
+            eprintln!("resize: {}", e);
+        }
+    }
 }
 
 // ============================================================================
 // Simulation State
 // ============================================================================
 
+#[derive(Debug, Copy, Clone, PartialEq)]
 pub enum SimulationState {
     Stopped,
     Running,
@@ -0,0 +1,313 @@
+//! blob-sim lineage
+//!
+//! Every blob ever born is recorded with its parents, its birth and its
+//! death generation, so the ancestry of a population can be traced back to
+//! the founders of the first generation.
+
+// ============================================================================
+// Imports
+// ============================================================================
+
+use super::blobs::Blob;
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::BTreeSet,
+    fs::File,
+    io::{self, BufWriter, Write},
+    path::Path,
+};
+
+// ============================================================================
+// Lineage
+// ============================================================================
+
+/// Records of all blobs of a run, ordered by id
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct Lineage {
+    records: Vec<LineageRecord>,
+}
+
+impl Lineage {
+    pub fn new() -> Lineage {
+        Lineage { records: vec![] }
+    }
+
+    pub fn clear(&mut self) {
+        self.records.clear();
+    }
+
+    /// Records the birth of `blob`, blobs have to be born in the order of
+    /// their ids
+    pub fn born(&mut self, blob: &Blob) {
+        self.records.push(LineageRecord {
+            id: blob.id(),
+            parents: blob.parents().to_vec(),
+            born: blob.born(),
+            died: None,
+        });
+    }
+
+    /// Records the death of the blob `id` in `generation`
+    pub fn died(&mut self, id: usize, generation: u32) {
+        if let Some(i) = self.index(id) {
+            self.records[i].died = Some(generation);
+        }
+    }
+
+    #[inline(always)]
+    pub fn records(&self) -> &[LineageRecord] {
+        &self.records
+    }
+    /// Record of the blob `id`
+    pub fn record(&self, id: usize) -> Option<&LineageRecord> {
+        self.index(id).map(|i| &self.records[i])
+    }
+
+    fn index(&self, id: usize) -> Option<usize> {
+        self.records.binary_search_by_key(&id, |r| r.id).ok()
+    }
+
+    // Queries
+    /// Ids of `id` and all its ancestors
+    pub fn ancestors(&self, id: usize) -> BTreeSet<usize> {
+        let mut ancestors = BTreeSet::new();
+        let mut open = vec![id];
+        while let Some(id) = open.pop() {
+            if ancestors.insert(id) {
+                if let Some(record) = self.record(id) {
+                    open.extend(&record.parents);
+                }
+            }
+        }
+        ancestors
+    }
+
+    /// Ancestors of `id` from the first generation, `id` itself if it is one
+    pub fn founders(&self, id: usize) -> BTreeSet<usize> {
+        self.ancestors(id)
+            .into_iter()
+            .filter(|&a| self.record(a).is_some_and(|r| r.parents.is_empty()))
+            .collect()
+    }
+
+    /// Most recent common ancestor of all `ids`
+    ///
+    /// A blob counts as its own ancestor. With sexual reproduction there may
+    /// be several common ancestors of the same age, ids grow with time so
+    /// the one with the highest id is taken.
+    pub fn mrca(&self, ids: &[usize]) -> Option<usize> {
+        let mut ids = ids.iter();
+        let mut common = self.ancestors(*ids.next()?);
+        for &id in ids {
+            let ancestors = self.ancestors(id);
+            common.retain(|a| ancestors.contains(a));
+        }
+        common.into_iter().next_back()
+    }
+
+    // Export
+    /// Writes the lineage as `output.nwk` and `output.lineage.csv`
+    pub fn save<P: AsRef<Path>>(&self, output: P) -> io::Result<()> {
+        let output = output.as_ref();
+        let mut newick = BufWriter::new(File::create(output.with_extension("nwk"))?);
+        self.write_newick(&mut newick)?;
+        newick.flush()?;
+        let mut edges = BufWriter::new(File::create(output.with_extension("lineage.csv"))?);
+        self.write_edges(&mut edges)?;
+        edges.flush()
+    }
+
+    /// Writes the lineage as a Newick tree
+    ///
+    /// Blobs are named `b<id>` and hang below their first parent, with the
+    /// generations between both births as branch length. The founders share
+    /// an unnamed root. Second parents of sexual reproduction only show up in
+    /// the edge list.
+    pub fn write_newick<W: Write>(&self, writer: &mut W) -> io::Result<()> {
+        let mut children: Vec<Vec<usize>> = vec![vec![]; self.records.len()];
+        let mut roots = vec![];
+        for (i, record) in self.records.iter().enumerate() {
+            match record.parents.first().and_then(|&p| self.index(p)) {
+                Some(parent) => children[parent].push(i),
+                None => roots.push(i),
+            }
+        }
+        let label = |i: usize| {
+            let record = &self.records[i];
+            match record.parents.first().and_then(|&p| self.record(p)) {
+                Some(parent) => format!("b{}:{}", record.id, record.born - parent.born),
+                None => format!("b{}", record.id),
+            }
+        };
+        // Walk the tree without recursion, lineages can get deep
+        enum Step {
+            Open(usize),
+            Close(usize),
+            Comma,
+        }
+        let mut stack = vec![];
+        let push_children = |stack: &mut Vec<Step>, nodes: &[usize]| {
+            for (k, &node) in nodes.iter().enumerate().rev() {
+                stack.push(Step::Open(node));
+                if k > 0 {
+                    stack.push(Step::Comma);
+                }
+            }
+        };
+        let wrap = roots.len() > 1;
+        if wrap {
+            write!(writer, "(")?;
+        }
+        push_children(&mut stack, &roots);
+        while let Some(step) = stack.pop() {
+            match step {
+                Step::Open(i) if children[i].is_empty() => write!(writer, "{}", label(i))?,
+                Step::Open(i) => {
+                    write!(writer, "(")?;
+                    stack.push(Step::Close(i));
+                    push_children(&mut stack, &children[i]);
+                }
+                Step::Close(i) => write!(writer, "){}", label(i))?,
+                Step::Comma => write!(writer, ",")?,
+            }
+        }
+        if wrap {
+            write!(writer, ")")?;
+        }
+        writeln!(writer, ";")
+    }
+
+    /// Writes one `parent,child` row per parent of every blob as CSV, with
+    /// the birth and death generation of the child
+    ///
+    /// Founders have a single row with an empty parent.
+    pub fn write_edges<W: Write>(&self, writer: &mut W) -> io::Result<()> {
+        writeln!(writer, "parent,child,born,died")?;
+        for record in &self.records {
+            let died = record.died.map(|d| d.to_string()).unwrap_or_default();
+            if record.parents.is_empty() {
+                writeln!(writer, ",{},{},{}", record.id, record.born, died)?;
+            }
+            for parent in &record.parents {
+                writeln!(writer, "{},{},{},{}", parent, record.id, record.born, died)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+// ============================================================================
+// Lineage Record
+// ============================================================================
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct LineageRecord {
+    pub id: usize,
+    /// Ids of the parents, none for founders
+    pub parents: Vec<usize>,
+    /// Generation the blob was born into
+    pub born: u32,
+    /// Generation the blob starved or was eaten in, `None` while alive
+    pub died: Option<u32>,
+}
+
+// ============================================================================
+// Testing
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Three generations, `5` is born from `1` and `0`
+    ///
+    /// ```text
+    /// 0 -+- 3 -- 6
+    ///    '- 4 -- 7
+    /// 1 ---- 5
+    /// 2
+    /// ```
+    fn lineage() -> Lineage {
+        let mut lineage = Lineage::new();
+        for (id, parents, born) in [
+            (0, vec![], 0),
+            (1, vec![], 0),
+            (2, vec![], 0),
+            (3, vec![0], 1),
+            (4, vec![0], 1),
+            (5, vec![1, 0], 1),
+            (6, vec![3], 2),
+            (7, vec![4], 2),
+        ] {
+            lineage.records.push(LineageRecord {
+                id,
+                parents,
+                born,
+                died: None,
+            });
+        }
+        lineage.died(2, 0);
+        lineage.died(1, 1);
+        lineage.died(4, 2);
+        lineage
+    }
+
+    #[test]
+    fn newick_hangs_blobs_below_their_first_parent() {
+        let mut newick = vec![];
+        lineage().write_newick(&mut newick).unwrap();
+        assert_eq!(
+            String::from_utf8(newick).unwrap(),
+            "(((b6:1)b3:1,(b7:1)b4:1)b0,(b5:1)b1,b2);\n"
+        );
+        // A single founder needs no extra root
+        let mut single = lineage();
+        single.records.retain(|r| r.id == 0 || r.id == 3);
+        let mut newick = vec![];
+        single.write_newick(&mut newick).unwrap();
+        assert_eq!(String::from_utf8(newick).unwrap(), "(b3:1)b0;\n");
+    }
+
+    #[test]
+    fn edges_list_every_parent() {
+        let mut csv = vec![];
+        lineage().write_edges(&mut csv).unwrap();
+        assert_eq!(
+            String::from_utf8(csv).unwrap(),
+            "parent,child,born,died\n\
+             ,0,0,\n\
+             ,1,0,1\n\
+             ,2,0,0\n\
+             0,3,1,\n\
+             0,4,1,2\n\
+             1,5,1,\n\
+             0,5,1,\n\
+             3,6,2,\n\
+             4,7,2,\n"
+        );
+    }
+
+    #[test]
+    fn mrca_is_the_youngest_common_ancestor() {
+        let lineage = lineage();
+        assert_eq!(lineage.mrca(&[6, 7]), Some(0));
+        assert_eq!(lineage.mrca(&[6, 3]), Some(3));
+        // Through the second parent
+        assert_eq!(lineage.mrca(&[5, 7]), Some(0));
+        assert_eq!(lineage.mrca(&[6]), Some(6));
+        assert_eq!(lineage.mrca(&[6, 2]), None);
+        assert_eq!(lineage.mrca(&[]), None);
+    }
+
+    #[test]
+    fn living_blobs_trace_back_to_their_founders() {
+        let lineage = lineage();
+        assert_eq!(lineage.founders(5), BTreeSet::from([0, 1]));
+        assert_eq!(lineage.founders(2), BTreeSet::from([2]));
+        let living: BTreeSet<usize> = [5, 6, 7]
+            .iter()
+            .flat_map(|&id| lineage.founders(id))
+            .collect();
+        assert_eq!(living, BTreeSet::from([0, 1]));
+    }
+}
@@ -10,14 +10,101 @@
 
 extern crate blob_sim;
 
+use blob_sim::{
+    batch::{self, Batch, Sweep},
+    blobs::Blob,
+    cli::{Cli, Command},
+    simulation::SimulationCore,
+};
 use ggez::*;
-use std::{env, path::PathBuf};
+use std::{collections::BTreeSet, env, error::Error, fs, path::PathBuf};
+use structopt::StructOpt;
 
 // ============================================================================
 // Main
 // ============================================================================
 
-fn main() -> GameResult {
+fn main() -> Result<(), Box<dyn Error>> {
+    match Cli::from_args().command() {
+        Command::Run {
+            settings,
+            resume,
+            snapshot,
+        } => run(resume.core(&settings)?, snapshot)?,
+        Command::Headless {
+            settings,
+            output,
+            resume,
+        } => {
+            if let Some(dir) = &output.output {
+                fs::create_dir_all(dir)?;
+            }
+            let mut core = resume.core(&settings)?;
+            println!("seed: {}", core.seed());
+            batch::run_headless(
+                &mut core,
+                output.generations,
+                output.output.map(|dir| dir.join("generations")).as_deref(),
+                output.format,
+                |stats| println!("{}", stats),
+            )?;
+            print_ancestry(&core);
+        }
+        Command::Batch {
+            settings,
+            output,
+            runs,
+            sweep,
+            jobs,
+        } => {
+            if let Some(dir) = &output.output {
+                fs::create_dir_all(dir)?;
+            }
+            if let Some(jobs) = jobs {
+                rayon::ThreadPoolBuilder::new()
+                    .num_threads(jobs)
+                    .build_global()?;
+            }
+            let sweep = match sweep {
+                Some(path) => Sweep::load(path)?,
+                None => Sweep::default(),
+            };
+            let batch = Batch::new(&settings.settings()?, &sweep, runs)?;
+            println!("runs: {}", batch.runs().len());
+            batch.run(output.generations, output.output.as_deref(), output.format)?;
+        }
+    }
+    Ok(())
+}
+
+/// Prints where the surviving blobs come from
+fn print_ancestry(core: &SimulationCore) {
+    let lineage = core.lineage();
+    let living: Vec<usize> = core.blobs().iter().map(Blob::id).collect();
+    match lineage.mrca(&living).and_then(|id| lineage.record(id)) {
+        Some(mrca) => println!(
+            "most recent common ancestor: {} born in generation {}",
+            mrca.id, mrca.born
+        ),
+        None => println!("most recent common ancestor: none"),
+    }
+    let founders: BTreeSet<usize> = living.iter().flat_map(|&id| lineage.founders(id)).collect();
+    println!(
+        "founders with living descendants: {}",
+        founders
+            .iter()
+            .map(|id| id.to_string())
+            .collect::<Vec<String>>()
+            .join(", ")
+    );
+}
+
+// ============================================================================
+// Window
+// ============================================================================
+
+fn run(core: SimulationCore, snapshot: PathBuf) -> GameResult {
+    let screen_size = core.settings().screen_size();
     let resource_dir = if let Ok(manifest_dir) = env::var("CARGO_MANIFEST_DIR") {
         let mut path = PathBuf::from(manifest_dir);
         path.push("resources");
@@ -35,19 +122,19 @@ fn main() -> GameResult {
             srgb: true,
         })
         .window_mode(conf::WindowMode {
-            width: blob_sim::settings::Settings::default().screen_size().0,
-            height: blob_sim::settings::Settings::default().screen_size().1,
+            width: screen_size.0,
+            height: screen_size.1,
             maximized: false,
             fullscreen_type: conf::FullscreenType::Windowed,
             borderless: false,
-            min_width: blob_sim::settings::Settings::default().screen_size().0,
+            min_width: 0.0,
             max_width: 0.0,
-            min_height: blob_sim::settings::Settings::default().screen_size().1,
+            min_height: 0.0,
             max_height: 0.0,
             resizable: true,
         });
     let (ctx, events_loop) = &mut ctx_builder.build().unwrap();
-    let sim = &mut blob_sim::Simulation::new(ctx);
-    sim.reset(true);
+    let sim = &mut blob_sim::Simulation::new(ctx, core, snapshot);
+    println!("seed: {}", sim.core().seed());
     event::run(ctx, events_loop, sim)
 }
@@ -5,6 +5,7 @@
 // Imports
 // ============================================================================
 
+use super::species::Species;
 use super::TILE_SIZE;
 use ggez::{graphics, nalgebra::Point2, Context};
 
@@ -18,6 +19,8 @@ use ggez::{graphics, nalgebra::Point2, Context};
 
 pub struct Resources {
     blob: graphics::Image,
+    /// Sprites of the species, `None` for the default blob
+    species: Vec<Option<graphics::Image>>,
     food: graphics::Image,
     map: [graphics::Image; 10],
 }
@@ -26,6 +29,7 @@ impl Resources {
     pub fn new(ctx: &mut Context) -> Resources {
         Resources {
             blob: graphics::Image::new(ctx, "/tiles/mapTile_136.png").unwrap(),
+            species: vec![],
             food: graphics::Image::new(ctx, "/tiles/mapTile_104.png").unwrap(),
             map: [
                 graphics::Image::new(ctx, "/tiles/mapTile_006.png").unwrap(),
@@ -45,6 +49,30 @@ impl Resources {
     pub fn blob(&self) -> &graphics::Image {
         &self.blob
     }
+    /// Sprite of the blobs of `species`
+    pub fn sprite(&self, species: usize) -> &graphics::Image {
+        self.species
+            .get(species)
+            .and_then(Option::as_ref)
+            .unwrap_or(&self.blob)
+    }
+    /// Loads the sprites of `species`, those that fail to load are drawn as
+    /// the default blob
+    pub fn load_species(&mut self, ctx: &mut Context, species: &[Species]) {
+        self.species = species
+            .iter()
+            .map(|s| {
+                s.sprite()
+                    .and_then(|path| match graphics::Image::new(ctx, path) {
+                        Ok(image) => Some(image),
+                        Err(e) => {
+                            eprintln!("species {}: {}: {}", s.name(), path, e);
+                            None
+                        }
+                    })
+            })
+            .collect();
+    }
     pub fn food(&self) -> &graphics::Image {
         &self.food
     }
@@ -0,0 +1,458 @@
+//! blob-sim selection rules
+//!
+//! A [`SelectionRule`] decides when a blob has found all the food it can use
+//! and what becomes of it at the end of a generation. Which rule a simulation
+//! uses is set by its [`Selection`] setting.
+
+// ============================================================================
+// Imports
+// ============================================================================
+
+use super::blobs::{Blob, GenerationResult};
+use super::settings::Settings;
+use rand::{Rng, RngCore};
+use serde::{Deserialize, Serialize};
+use std::convert::TryFrom;
+
+// ============================================================================
+// Selection Rule
+// ============================================================================
+
+pub trait SelectionRule {
+    /// Whether more food would not change the outcome for `blob`, so it
+    /// heads home
+    fn satisfied(&self, blob: &Blob, settings: &Settings) -> bool;
+
+    /// Outcome for `blob` at the end of a generation
+    fn select(&self, blob: &Blob, settings: &Settings, rng: &mut dyn RngCore) -> GenerationResult;
+}
+
+/// Fixed amounts of food to survive and to reproduce
+///
+/// Every `surplus` food beyond `reproduce` adds another offspring, up to
+/// `max_offspring`. With a `surplus` of `0` there is a single offspring.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ThresholdSelection {
+    pub survive: u8,
+    pub reproduce: u8,
+    pub surplus: u8,
+    pub max_offspring: u32,
+}
+
+impl ThresholdSelection {
+    /// Food needed for the most offspring
+    fn enough(&self) -> u32 {
+        u32::from(self.reproduce) + u32::from(self.surplus) * self.max_offspring.saturating_sub(1)
+    }
+}
+
+impl SelectionRule for ThresholdSelection {
+    fn satisfied(&self, blob: &Blob, _settings: &Settings) -> bool {
+        u32::from(blob.food_found()) >= self.enough()
+    }
+
+    fn select(
+        &self,
+        blob: &Blob,
+        _settings: &Settings,
+        _rng: &mut dyn RngCore,
+    ) -> GenerationResult {
+        let food = blob.food_found();
+        if food < self.survive {
+            GenerationResult::Starve
+        } else if food < self.reproduce || self.max_offspring == 0 {
+            GenerationResult::Live
+        } else {
+            let extra = match self.surplus {
+                0 => 0,
+                surplus => u32::from((food - self.reproduce) / surplus),
+            };
+            GenerationResult::Reproduce((1 + extra).min(self.max_offspring))
+        }
+    }
+}
+
+/// Chances to survive and to have an offspring by the food found
+///
+/// Both curves list the probability for 0, 1, 2, ... food, the last entry
+/// holds for any more food. Curves are not empty and never drop with more
+/// food.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ProbabilisticSelection {
+    pub survival: Vec<f32>,
+    pub reproduction: Vec<f32>,
+}
+
+impl ProbabilisticSelection {
+    fn validate(&self) -> Result<(), String> {
+        for (name, curve) in [
+            ("survival", &self.survival),
+            ("reproduction", &self.reproduction),
+        ] {
+            if curve.is_empty() {
+                return Err(format!("{} curve is empty", name));
+            }
+            if !curve.iter().all(|p| (0.0..=1.0).contains(p)) {
+                return Err(format!("{} curve has chances outside 0 to 1", name));
+            }
+            if !curve.windows(2).all(|p| p[0] <= p[1]) {
+                return Err(format!("{} curve drops with more food", name));
+            }
+        }
+        Ok(())
+    }
+}
+
+impl SelectionRule for ProbabilisticSelection {
+    fn satisfied(&self, blob: &Blob, _settings: &Settings) -> bool {
+        let last = self.survival.len().max(self.reproduction.len());
+        usize::from(blob.food_found()) + 1 >= last
+    }
+
+    fn select(&self, blob: &Blob, _settings: &Settings, rng: &mut dyn RngCore) -> GenerationResult {
+        let chance = |curve: &[f32]| {
+            curve
+                .get(usize::from(blob.food_found()))
+                .or_else(|| curve.last())
+                .copied()
+                .unwrap_or(0.0)
+        };
+        if rng.gen::<f32>() >= chance(&self.survival) {
+            GenerationResult::Starve
+        } else if rng.gen::<f32>() < chance(&self.reproduction) {
+            GenerationResult::Reproduce(1)
+        } else {
+            GenerationResult::Live
+        }
+    }
+}
+
+/// `survive` food to survive, then one offspring for every
+/// `offspring_energy` times the starting energy left, up to `max_offspring`
+#[derive(Debug, Clone, PartialEq)]
+pub struct EnergySelection {
+    pub survive: u8,
+    pub offspring_energy: f32,
+    pub max_offspring: u32,
+}
+
+impl EnergySelection {
+    fn offspring(&self, blob: &Blob, settings: &Settings) -> u32 {
+        let energy = self.offspring_energy * settings.blob_energy();
+        if energy > 0.0 {
+            ((blob.energy() / energy).max(0.0) as u32).min(self.max_offspring)
+        } else {
+            self.max_offspring
+        }
+    }
+}
+
+impl SelectionRule for EnergySelection {
+    fn satisfied(&self, blob: &Blob, settings: &Settings) -> bool {
+        blob.food_found() >= self.survive && self.offspring(blob, settings) >= self.max_offspring
+    }
+
+    fn select(&self, blob: &Blob, settings: &Settings, _rng: &mut dyn RngCore) -> GenerationResult {
+        if blob.food_found() < self.survive {
+            GenerationResult::Starve
+        } else {
+            match self.offspring(blob, settings) {
+                0 => GenerationResult::Live,
+                offspring => GenerationResult::Reproduce(offspring),
+            }
+        }
+    }
+}
+
+// ============================================================================
+// Selection
+// ============================================================================
+
+/// Selection rule of a simulation, as set in the settings
+///
+/// In a config file the rule is a table naming it, along with its
+/// parameters:
+///
+/// ```toml
+/// [selection]
+/// rule = "threshold"
+/// survive = 1
+/// reproduce = 2
+/// surplus = 2
+/// max_offspring = 3
+/// ```
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(try_from = "SelectionConfig", into = "SelectionConfig")]
+pub enum Selection {
+    Threshold(ThresholdSelection),
+    Probabilistic(ProbabilisticSelection),
+    Energy(EnergySelection),
+}
+
+impl Selection {
+    pub fn rule(&self) -> &dyn SelectionRule {
+        match self {
+            Selection::Threshold(rule) => rule,
+            Selection::Probabilistic(rule) => rule,
+            Selection::Energy(rule) => rule,
+        }
+    }
+
+    /// Checks the parameters of the rule, done with the settings
+    pub fn validate(&self) -> Result<(), String> {
+        match self {
+            Selection::Probabilistic(rule) => rule.validate(),
+            Selection::Threshold(_) | Selection::Energy(_) => Ok(()),
+        }
+    }
+}
+
+impl Default for Selection {
+    /// One food to survive, two for an offspring
+    fn default() -> Selection {
+        Selection::Threshold(ThresholdSelection {
+            survive: 1,
+            reproduce: 2,
+            surplus: 0,
+            max_offspring: 1,
+        })
+    }
+}
+
+/// [`Selection`] as written to config files
+#[derive(Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct SelectionConfig {
+    rule: String,
+    survive: Option<u8>,
+    reproduce: Option<u8>,
+    surplus: Option<u8>,
+    max_offspring: Option<u32>,
+    survival: Option<Vec<f32>>,
+    reproduction: Option<Vec<f32>>,
+    offspring_energy: Option<f32>,
+}
+
+impl TryFrom<SelectionConfig> for Selection {
+    type Error = String;
+
+    fn try_from(config: SelectionConfig) -> Result<Selection, String> {
+        match config.rule.as_str() {
+            "threshold" => Ok(Selection::Threshold(ThresholdSelection {
+                survive: config.survive.unwrap_or(1),
+                reproduce: config.reproduce.unwrap_or(2),
+                surplus: config.surplus.unwrap_or(0),
+                max_offspring: config.max_offspring.unwrap_or(1),
+            })),
+            "probabilistic" => Ok(Selection::Probabilistic(ProbabilisticSelection {
+                survival: config
+                    .survival
+                    .ok_or("probabilistic selection needs a survival curve")?,
+                reproduction: config
+                    .reproduction
+                    .ok_or("probabilistic selection needs a reproduction curve")?,
+            })),
+            "energy" => Ok(Selection::Energy(EnergySelection {
+                survive: config.survive.unwrap_or(1),
+                offspring_energy: config
+                    .offspring_energy
+                    .ok_or("energy selection needs an offspring_energy")?,
+                max_offspring: config.max_offspring.unwrap_or(1),
+            })),
+            rule => Err(format!(
+                "unknown selection {}, use threshold, probabilistic or energy",
+                rule
+            )),
+        }
+    }
+}
+
+impl From<Selection> for SelectionConfig {
+    fn from(selection: Selection) -> SelectionConfig {
+        let mut config = SelectionConfig {
+            rule: String::new(),
+            survive: None,
+            reproduce: None,
+            surplus: None,
+            max_offspring: None,
+            survival: None,
+            reproduction: None,
+            offspring_energy: None,
+        };
+        match selection {
+            Selection::Threshold(rule) => {
+                config.rule = "threshold".to_owned();
+                config.survive = Some(rule.survive);
+                config.reproduce = Some(rule.reproduce);
+                config.surplus = Some(rule.surplus);
+                config.max_offspring = Some(rule.max_offspring);
+            }
+            Selection::Probabilistic(rule) => {
+                config.rule = "probabilistic".to_owned();
+                config.survival = Some(rule.survival);
+                config.reproduction = Some(rule.reproduction);
+            }
+            Selection::Energy(rule) => {
+                config.rule = "energy".to_owned();
+                config.survive = Some(rule.survive);
+                config.offspring_energy = Some(rule.offspring_energy);
+                config.max_offspring = Some(rule.max_offspring);
+            }
+        }
+        config
+    }
+}
+
+// ============================================================================
+// Testing
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::simulation::SimulationRng;
+    use rand::SeedableRng;
+
+    fn settings() -> Settings {
+        let mut settings = Settings::default();
+        settings.set_food_energy(120.0);
+        settings
+    }
+
+    /// Founder that has eaten `food` food
+    fn blob(food: u8, settings: &Settings) -> Blob {
+        let mut blob = Blob::new(0, 0, settings, &mut SimulationRng::seed_from_u64(1));
+        for _ in 0..food {
+            blob.eat(settings);
+        }
+        blob
+    }
+
+    fn outcomes(rule: &dyn SelectionRule, food: u8) -> Vec<GenerationResult> {
+        let settings = settings();
+        let mut rng = SimulationRng::seed_from_u64(2);
+        (0..=food)
+            .map(|f| rule.select(&blob(f, &settings), &settings, &mut rng))
+            .collect()
+    }
+
+    fn satisfied(rule: &dyn SelectionRule, food: u8) -> Vec<bool> {
+        let settings = settings();
+        (0..=food)
+            .map(|f| rule.satisfied(&blob(f, &settings), &settings))
+            .collect()
+    }
+
+    #[test]
+    fn default_rule_is_the_baseline() {
+        use GenerationResult::*;
+        let selection = Selection::default();
+        assert_eq!(
+            outcomes(selection.rule(), 3),
+            [Starve, Live, Reproduce(1), Reproduce(1)]
+        );
+        assert_eq!(satisfied(selection.rule(), 2), [false, false, true]);
+    }
+
+    #[test]
+    fn threshold_surplus_adds_offspring() {
+        use GenerationResult::*;
+        let rule = ThresholdSelection {
+            survive: 1,
+            reproduce: 2,
+            surplus: 2,
+            max_offspring: 3,
+        };
+        assert_eq!(
+            outcomes(&rule, 8),
+            [
+                Starve,
+                Live,
+                Reproduce(1),
+                Reproduce(1),
+                Reproduce(2),
+                Reproduce(2),
+                Reproduce(3),
+                Reproduce(3),
+                Reproduce(3)
+            ]
+        );
+        assert_eq!(
+            satisfied(&rule, 6),
+            [false, false, false, false, false, false, true]
+        );
+        let barren = ThresholdSelection {
+            max_offspring: 0,
+            ..rule
+        };
+        assert_eq!(outcomes(&barren, 3), [Starve, Live, Live, Live]);
+    }
+
+    #[test]
+    fn probabilistic_rule_follows_the_curves() {
+        use GenerationResult::*;
+        let rule = ProbabilisticSelection {
+            survival: vec![0.0, 1.0],
+            reproduction: vec![0.0, 0.0, 1.0],
+        };
+        // Certain chances, the last entry holds for more food
+        assert_eq!(
+            outcomes(&rule, 4),
+            [Starve, Live, Reproduce(1), Reproduce(1), Reproduce(1)]
+        );
+        assert_eq!(satisfied(&rule, 2), [false, false, true]);
+        // Even chances
+        let rule = ProbabilisticSelection {
+            survival: vec![0.5],
+            reproduction: vec![0.0],
+        };
+        let settings = settings();
+        let mut rng = SimulationRng::seed_from_u64(3);
+        let survivors = (0..1000)
+            .filter(|_| rule.select(&blob(0, &settings), &settings, &mut rng) == Live)
+            .count();
+        assert!((400..600).contains(&survivors));
+    }
+
+    #[test]
+    fn bad_curves_are_rejected() {
+        let curves = |survival: Vec<f32>, reproduction: Vec<f32>| {
+            Selection::Probabilistic(ProbabilisticSelection {
+                survival,
+                reproduction,
+            })
+            .validate()
+        };
+        assert!(curves(vec![0.2, 1.0], vec![0.0, 0.5]).is_ok());
+        assert!(curves(vec![], vec![0.5]).is_err());
+        assert!(curves(vec![1.0, 0.2], vec![0.5]).is_err());
+        assert!(curves(vec![0.5], vec![0.5, 1.5]).is_err());
+        let mut settings = Settings::default();
+        settings.set_selection(Selection::Probabilistic(ProbabilisticSelection {
+            survival: vec![],
+            reproduction: vec![1.0],
+        }));
+        assert!(settings.validate().is_err());
+    }
+
+    #[test]
+    fn energy_rule_counts_offspring_by_energy() {
+        use GenerationResult::*;
+        // Starting energy of 480, 120 more per food
+        let rule = EnergySelection {
+            survive: 1,
+            offspring_energy: 0.5,
+            max_offspring: 3,
+        };
+        assert_eq!(
+            outcomes(&rule, 3),
+            [Starve, Reproduce(2), Reproduce(3), Reproduce(3)]
+        );
+        assert_eq!(satisfied(&rule, 3), [false, false, true, true]);
+        let poor = EnergySelection {
+            offspring_energy: 4.0,
+            ..rule
+        };
+        assert_eq!(outcomes(&poor, 1), [Starve, Live]);
+    }
+}
@@ -5,7 +5,13 @@
 // Imports
 // ============================================================================
 
+use super::genome::{mutation::Mutation, Gene, Genes};
+use super::selection::Selection;
+use super::simulation::MAX_SEED;
+use super::species::Species;
 use super::TILE_SIZE;
+use serde::{Deserialize, Serialize};
+use std::{collections::BTreeMap, convert::TryFrom, error, fmt, fs, io, path::Path, str::FromStr};
 
 // ============================================================================
 // Constants
@@ -15,51 +21,240 @@ use super::TILE_SIZE;
 // Settings
 // ============================================================================
 
-#[derive(Clone, PartialEq)]
+/// Simulation parameters
+///
+/// Settings (de)serialize to TOML, fields missing from a config file keep
+/// their default value.
+#[derive(Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
 pub struct Settings {
     // Simulation
+    #[serde(rename = "screen")]
     sim_screen: (f32, f32),
+    #[serde(rename = "world")]
+    sim_world: (f32, f32),
+    #[serde(rename = "fps")]
     sim_fps: u32,
+    #[serde(rename = "tick_rate")]
+    sim_tick_rate: u32,
+    #[serde(rename = "start_blobs")]
     sim_start_blobs: u32,
+    #[serde(rename = "food_energy")]
     sim_food_energy: f32,
+    #[serde(rename = "seed")]
+    sim_seed: Option<u64>,
     // Generation
     gen_duration: u32,
-    gen_food: u32,
+    #[serde(rename = "food_decay")]
     gen_food_decay: (u32, u32, u32),
+    #[serde(rename = "return_home")]
+    gen_return_home: bool,
     // Blob
     blob_energy: f32,
-    blob_speed: (f32, f32),
-    blob_sense: (f32, f32),
-    blob_size: (f32, f32),
+    // Predation
+    predation: bool,
+    predation_ratio: f32,
+    // Reproduction
+    reproduction: Reproduction,
+    crossover: Crossover,
+    mutation: Mutation,
+    // Selection
+    selection: Selection,
+    // Genes and species
+    genes: Genes,
+    species: Vec<Species>,
 }
 
 impl Settings {
+    // Config
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Settings, SettingsError> {
+        let settings: Settings = toml::from_str(&fs::read_to_string(path)?)?;
+        settings.validate()?;
+        Ok(settings)
+    }
+    /// Checks that the settings make sense together, done whenever they are
+    /// loaded or overridden
+    pub fn validate(&self) -> Result<(), SettingsError> {
+        for (name, value) in [
+            ("fps", self.sim_fps),
+            ("tick_rate", self.sim_tick_rate),
+            ("gen_duration", self.gen_duration),
+        ] {
+            if value == 0 {
+                return Err(SettingsError::Invalid(format!(
+                    "{} has to be above 0",
+                    name
+                )));
+            }
+        }
+        if self
+            .sim_tick_rate
+            .checked_mul(self.gen_duration)
+            .is_none_or(|ticks| ticks == u32::MAX)
+        {
+            return Err(SettingsError::Invalid(format!(
+                "tick_rate {} times gen_duration {} is too many ticks",
+                self.sim_tick_rate, self.gen_duration
+            )));
+        }
+        for (name, (width, height)) in [("screen", self.sim_screen), ("world", self.sim_world)] {
+            if !(width.is_finite() && height.is_finite() && width > 0.0 && height > 0.0) {
+                return Err(SettingsError::Invalid(format!(
+                    "{} size {}x{} has to be above 0",
+                    name, width, height
+                )));
+            }
+        }
+        if self.sim_seed.is_some_and(|seed| seed > MAX_SEED) {
+            return Err(SettingsError::Invalid(format!(
+                "seed has to be at most {}",
+                MAX_SEED
+            )));
+        }
+        if self.predation_ratio <= 1.0 || self.predation_ratio.is_nan() {
+            return Err(SettingsError::Invalid(format!(
+                "predation_ratio {} has to be above 1, blobs would hunt equal or larger ones",
+                self.predation_ratio
+            )));
+        }
+        let species = self.species_settings()?;
+        for settings in std::iter::once(self).chain(&species) {
+            settings
+                .selection()
+                .validate()
+                .map_err(SettingsError::Invalid)?;
+            // Genes changed after loading, e.g. by a species, are checked again
+            for gene in settings.genes().iter() {
+                gene.validate()
+                    .map_err(|e| SettingsError::Invalid(e.to_string()))?;
+            }
+            if let Mutation::Covariance(model) = settings.mutation() {
+                if model.matrix().len() != settings.genes().len() {
+                    return Err(SettingsError::Invalid(format!(
+                        "covariance matrix has {} rows for {} genes",
+                        model.matrix().len(),
+                        settings.genes().len()
+                    )));
+                }
+            }
+        }
+        Ok(())
+    }
+    /// Writes the settings as TOML
+    ///
+    /// The settings go through a [`toml::Value`] first, which writes plain
+    /// values before tables whatever the order of the fields. An empty
+    /// species list is a plain value, a filled one an array of tables.
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> Result<(), SettingsError> {
+        Ok(fs::write(
+            path,
+            toml::to_string(&toml::Value::try_from(self)?)?,
+        )?)
+    }
+    /// Copy with the fields named like in the config file replaced
+    ///
+    /// Fields of tables are named by a dotted path like `mutation.model`,
+    /// entries of a list by their name like `genes.speed.mutation` or
+    /// `species.fast.genes.speed.base`.
+    pub fn with_overrides(
+        &self,
+        overrides: &BTreeMap<String, toml::Value>,
+    ) -> Result<Settings, SettingsError> {
+        let mut fields = match toml::Value::try_from(self)? {
+            toml::Value::Table(fields) => fields,
+            _ => unreachable!(),
+        };
+        for (key, value) in overrides {
+            let head = key.split('.').next().unwrap_or_default();
+            if head != "seed" && !fields.contains_key(head) {
+                return Err(SettingsError::Unknown(key.clone()));
+            }
+            // Unknown fields are caught when deserializing
+            if !set_field(&mut fields, key, value.clone()) {
+                return Err(SettingsError::Unknown(key.clone()));
+            }
+        }
+        let settings: Settings = toml::Value::Table(fields).try_into()?;
+        settings.validate()?;
+        Ok(settings)
+    }
+
     // Simulation
+    /// Size the window opens at, never larger than the map
     #[inline(always)]
     pub fn screen_size(&self) -> (f32, f32) {
-        self.sim_screen
+        let map_size = self.map_size();
+        (
+            self.sim_screen.0.min(map_size.0),
+            self.sim_screen.1.min(map_size.1),
+        )
     }
     #[inline(always)]
+    pub fn set_screen_size(&mut self, size: (f32, f32)) {
+        self.sim_screen = size;
+    }
+    /// Size of the area blobs and food are in
+    #[inline(always)]
     pub fn world_size(&self) -> (f32, f32) {
+        self.sim_world
+    }
+    #[inline(always)]
+    pub fn set_world_size(&mut self, size: (f32, f32)) {
+        self.sim_world = size;
+    }
+    /// Size of the world with a tile of wall on every side
+    #[inline(always)]
+    pub fn map_size(&self) -> (f32, f32) {
         (
-            self.sim_screen.0 - (2.0 * TILE_SIZE),
-            self.sim_screen.1 - (2.0 * TILE_SIZE),
+            self.sim_world.0 + (2.0 * TILE_SIZE),
+            self.sim_world.1 + (2.0 * TILE_SIZE),
         )
     }
 
+    /// Frames rendered per second, has no effect on the simulation itself
     #[inline(always)]
     pub fn fps(&self) -> u32 {
         self.sim_fps
     }
+    #[inline(always)]
+    pub fn set_fps(&mut self, fps: u32) {
+        self.sim_fps = fps;
+    }
+    /// Simulation ticks per simulated second
+    #[inline(always)]
+    pub fn tick_rate(&self) -> u32 {
+        self.sim_tick_rate
+    }
+    #[inline(always)]
+    pub fn set_tick_rate(&mut self, tick_rate: u32) {
+        self.sim_tick_rate = tick_rate;
+    }
 
     #[inline(always)]
     pub fn start_blobs(&self) -> u32 {
         self.sim_start_blobs
     }
     #[inline(always)]
+    pub fn set_start_blobs(&mut self, blobs: u32) {
+        self.sim_start_blobs = blobs;
+    }
+    #[inline(always)]
     pub fn food_energy(&self) -> f32 {
         self.sim_food_energy
     }
+    #[inline(always)]
+    pub fn set_food_energy(&mut self, energy: f32) {
+        self.sim_food_energy = energy;
+    }
+    /// Seed for the simulation RNG, `None` picks a random one per run
+    #[inline(always)]
+    pub fn seed(&self) -> Option<u64> {
+        self.sim_seed
+    }
+    #[inline(always)]
+    pub fn set_seed(&mut self, seed: Option<u64>) {
+        self.sim_seed = seed;
+    }
 
     // Generation
     #[inline(always)]
@@ -67,18 +262,41 @@ impl Settings {
         self.gen_duration
     }
     #[inline(always)]
-    pub fn food_per_gen(&self) -> u32 {
-        self.gen_food
+    pub fn set_gen_duration(&mut self, duration: u32) {
+        self.gen_duration = duration;
+    }
+    /// Food placed in the first generation
+    #[inline(always)]
+    pub fn start_food(&self) -> u32 {
+        self.gen_food_decay.0
     }
+    /// Food placed in the generation following one with `food` food
     #[inline(always)]
-    pub fn decay_food(&mut self) {
-        if self.gen_food > self.gen_food_decay.2 {
-            self.gen_food = self.gen_food - self.gen_food_decay.1;
+    pub fn decay_food(&self, food: u32) -> u32 {
+        if food > self.gen_food_decay.2 {
+            food.saturating_sub(self.gen_food_decay.1)
+        } else {
+            food
         }
     }
+    /// Food placed in the first generation, the amount it shrinks by each
+    /// generation and the amount below which it no longer shrinks
     #[inline(always)]
-    pub fn reset_food(&mut self) {
-        self.gen_food = self.gen_food_decay.0;
+    pub fn food_decay(&self) -> (u32, u32, u32) {
+        self.gen_food_decay
+    }
+    #[inline(always)]
+    pub fn set_food_decay(&mut self, decay: (u32, u32, u32)) {
+        self.gen_food_decay = decay;
+    }
+    /// Whether blobs out in the field at the end of a generation starve
+    #[inline(always)]
+    pub fn return_home(&self) -> bool {
+        self.gen_return_home
+    }
+    #[inline(always)]
+    pub fn set_return_home(&mut self, return_home: bool) {
+        self.gen_return_home = return_home;
     }
 
     // Blob
@@ -87,20 +305,125 @@ impl Settings {
         self.blob_energy
     }
     #[inline(always)]
-    pub fn blob_speed(&self) -> (f32, f32) {
-        self.blob_speed
+    pub fn set_blob_energy(&mut self, energy: f32) {
+        self.blob_energy = energy;
+    }
+
+    // Predation
+    /// Whether larger blobs hunt smaller ones
+    #[inline(always)]
+    pub fn predation(&self) -> bool {
+        self.predation
+    }
+    #[inline(always)]
+    pub fn set_predation(&mut self, predation: bool) {
+        self.predation = predation;
+    }
+    /// Factor a blob has to be larger than another to eat it
+    #[inline(always)]
+    pub fn predation_ratio(&self) -> f32 {
+        self.predation_ratio
+    }
+    #[inline(always)]
+    pub fn set_predation_ratio(&mut self, ratio: f32) {
+        self.predation_ratio = ratio;
+    }
+
+    // Reproduction
+    #[inline(always)]
+    pub fn reproduction(&self) -> Reproduction {
+        self.reproduction
+    }
+    #[inline(always)]
+    pub fn set_reproduction(&mut self, reproduction: Reproduction) {
+        self.reproduction = reproduction;
+    }
+    /// How offspring inherit the traits of two parents
+    #[inline(always)]
+    pub fn crossover(&self) -> Crossover {
+        self.crossover
+    }
+    #[inline(always)]
+    pub fn set_crossover(&mut self, crossover: Crossover) {
+        self.crossover = crossover;
+    }
+    /// How the genome of an offspring mutates
+    #[inline(always)]
+    pub fn mutation(&self) -> &Mutation {
+        &self.mutation
+    }
+    #[inline(always)]
+    pub fn set_mutation(&mut self, mutation: Mutation) {
+        self.mutation = mutation;
     }
+
+    // Selection
+    /// Who survives and reproduces at the end of a generation
     #[inline(always)]
-    pub fn blob_sense(&self) -> (f32, f32) {
-        self.blob_sense
+    pub fn selection(&self) -> &Selection {
+        &self.selection
     }
     #[inline(always)]
-    pub fn blob_size(&self) -> (f32, f32) {
-        self.blob_size
+    pub fn set_selection(&mut self, selection: Selection) {
+        self.selection = selection;
     }
+
+    // Genes
+    /// Traits of the blobs, see [`genome`](super::genome)
+    #[inline(always)]
+    pub fn genes(&self) -> &Genes {
+        &self.genes
+    }
+    #[inline(always)]
+    pub fn set_genes(&mut self, genes: Genes) {
+        self.genes = genes;
+    }
+    /// Gene called `name`
+    #[inline(always)]
+    pub fn gene_mut(&mut self, name: &str) -> Option<&mut Gene> {
+        self.genes.gene_mut(name)
+    }
+
+    // Species
+    /// Species competing in the world, see [`species`](super::species)
+    ///
+    /// Without species all blobs use these settings.
+    #[inline(always)]
+    pub fn species(&self) -> &[Species] {
+        &self.species
+    }
+    #[inline(always)]
+    pub fn set_species(&mut self, species: Vec<Species>) {
+        self.species = species;
+    }
+    /// Settings the blobs of every species use, these with the overrides of
+    /// the species applied
+    ///
+    /// Without species there is a single entry with these settings.
+    pub fn species_settings(&self) -> Result<Vec<Settings>, SettingsError> {
+        let mut base = self.clone();
+        base.species.clear();
+        if self.species.is_empty() {
+            return Ok(vec![base]);
+        }
+        let mut settings = vec![];
+        for (i, species) in self.species.iter().enumerate() {
+            if self.species[..i].iter().any(|s| s.name() == species.name()) {
+                return Err(SettingsError::Invalid(format!(
+                    "species {} is listed twice",
+                    species.name()
+                )));
+            }
+            let mut s = base.clone();
+            species.apply(&mut s).map_err(SettingsError::Invalid)?;
+            settings.push(s);
+        }
+        Ok(settings)
+    }
+
     #[inline(always)]
     pub fn blob_step(&self) -> f32 {
-        ((self.world_size().0 / 2.0) / self.gen_duration() as f32) / self.fps() as f32
+        ((self.world_size().0 / 2.0) / self.gen_duration() as f32) / self.tick_rate() as f32
     }
 }
 
@@ -110,18 +433,291 @@ impl Default for Settings {
         Settings {
             // Simulation
             sim_screen: (size, size),
+            sim_world: (size - (2.0 * TILE_SIZE), size - (2.0 * TILE_SIZE)),
             sim_fps: 60,
+            sim_tick_rate: 60,
             sim_start_blobs: 8,
             sim_food_energy: 0.0,
+            sim_seed: None,
             // Generation
             gen_duration: 5,
-            gen_food: 100,
             gen_food_decay: (100, 1, 25),
+            gen_return_home: true,
             // Blob
             blob_energy: size / 2.0,
-            blob_speed: (1.0, 0.5),
-            blob_sense: (size / 7.5, 0.5),
-            blob_size: (1.0, 0.5),
+            // Predation
+            predation: false,
+            predation_ratio: 1.2,
+            // Reproduction
+            reproduction: Reproduction::Asexual,
+            crossover: Crossover::Average,
+            mutation: Mutation::Uniform,
+            // Selection
+            selection: Selection::default(),
+            // Genes and species
+            genes: default_genes(size),
+            species: vec![],
+        }
+    }
+}
+
+/// Speed and size make moving more expensive, sensing is free
+fn default_genes(size: f32) -> Genes {
+    let mut speed = Gene::new("speed", 1.0, 0.5);
+    speed.set_cost(2.0);
+    let sense = Gene::new("sense", size / 7.5, 0.5);
+    let mut size = Gene::new("size", 1.0, 0.5);
+    size.set_cost(3.0);
+    Genes::try_from(vec![speed, sense, size]).unwrap()
+}
+
+/// Sets the field at the dotted `path` in `table`, creating missing tables
+///
+/// Entries of arrays of tables are picked by their `name`. Returns whether
+/// the path could be followed.
+fn set_field(table: &mut toml::value::Table, path: &str, value: toml::Value) -> bool {
+    let (head, rest) = match path.split_once('.') {
+        Some(split) => split,
+        None => {
+            table.insert(path.to_owned(), value);
+            return true;
+        }
+    };
+    match table
+        .entry(head.to_owned())
+        .or_insert_with(|| toml::Value::Table(toml::value::Table::new()))
+    {
+        toml::Value::Table(table) => set_field(table, rest, value),
+        toml::Value::Array(items) => {
+            let (name, rest) = match rest.split_once('.') {
+                Some(split) => split,
+                None => return false,
+            };
+            items
+                .iter_mut()
+                .find(|item| item.get("name").and_then(toml::Value::as_str) == Some(name))
+                .and_then(toml::Value::as_table_mut)
+                .is_some_and(|item| set_field(item, rest, value))
+        }
+        _ => false,
+    }
+}
+
+// ============================================================================
+// Reproduction
+// ============================================================================
+
+#[derive(Debug, Copy, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Reproduction {
+    /// Offspring are mutated copies of a single parent
+    Asexual,
+    /// Blobs have to meet a mate to reproduce, offspring are a crossover of
+    /// both parents
+    Sexual,
+}
+
+impl FromStr for Reproduction {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Reproduction, String> {
+        match s {
+            "asexual" => Ok(Reproduction::Asexual),
+            "sexual" => Ok(Reproduction::Sexual),
+            _ => Err(format!("unknown reproduction {}, use asexual or sexual", s)),
+        }
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Crossover {
+    /// Mean of both parents
+    Average,
+    /// Every trait from one of the parents, chosen at random
+    RandomParent,
+    /// Every trait at a random point between both parents
+    Blend,
+}
+
+impl FromStr for Crossover {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Crossover, String> {
+        match s {
+            "average" => Ok(Crossover::Average),
+            "random_parent" => Ok(Crossover::RandomParent),
+            "blend" => Ok(Crossover::Blend),
+            _ => Err(format!(
+                "unknown crossover {}, use average, random_parent or blend",
+                s
+            )),
+        }
+    }
+}
+
+// ============================================================================
+// Settings Error
+// ============================================================================
+
+#[derive(Debug)]
+pub enum SettingsError {
+    Io(io::Error),
+    Parse(toml::de::Error),
+    Serialize(toml::ser::Error),
+    /// No setting with that name
+    Unknown(String),
+    /// Settings that do not fit together
+    Invalid(String),
+}
+
+impl fmt::Display for SettingsError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            SettingsError::Io(e) => write!(f, "settings io: {}", e),
+            SettingsError::Parse(e) => write!(f, "settings parse: {}", e),
+            SettingsError::Serialize(e) => write!(f, "settings serialize: {}", e),
+            SettingsError::Unknown(key) => write!(f, "unknown setting {}", key),
+            SettingsError::Invalid(e) => write!(f, "invalid settings: {}", e),
+        }
+    }
+}
+
+impl error::Error for SettingsError {}
+
+impl From<io::Error> for SettingsError {
+    fn from(e: io::Error) -> SettingsError {
+        SettingsError::Io(e)
+    }
+}
+
+impl From<toml::de::Error> for SettingsError {
+    fn from(e: toml::de::Error) -> SettingsError {
+        SettingsError::Parse(e)
+    }
+}
+
+impl From<toml::ser::Error> for SettingsError {
+    fn from(e: toml::ser::Error) -> SettingsError {
+        SettingsError::Serialize(e)
+    }
+}
+
+// ============================================================================
+// Testing
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::species::GeneOverride;
+    use std::{env, path::PathBuf};
+
+    /// File in the temp directory unique to this process and `name`
+    fn temp_file(name: &str) -> PathBuf {
+        env::temp_dir().join(format!("blob_sim_{}_{}.toml", std::process::id(), name))
+    }
+
+    fn roundtrip(settings: &Settings, name: &str) -> (Settings, String) {
+        let path = temp_file(name);
+        settings.save(&path).unwrap();
+        let text = fs::read_to_string(&path).unwrap();
+        let loaded = Settings::load(&path).unwrap();
+        fs::remove_file(&path).unwrap();
+        (loaded, text)
+    }
+
+    #[test]
+    fn default_settings_roundtrip() {
+        let settings = Settings::default();
+        let (loaded, _) = roundtrip(&settings, "default");
+        assert!(loaded == settings);
+    }
+
+    #[test]
+    fn large_seed_roundtrip() {
+        let mut settings = Settings::default();
+        settings.set_seed(Some(MAX_SEED));
+        let (loaded, _) = roundtrip(&settings, "large_seed");
+        assert_eq!(loaded.seed(), Some(MAX_SEED));
+        settings.set_seed(Some(MAX_SEED + 1));
+        assert!(matches!(
+            settings.validate(),
+            Err(SettingsError::Invalid(_))
+        ));
+    }
+
+    #[test]
+    fn zero_rates_are_rejected() {
+        let mut overrides = BTreeMap::new();
+        for key in ["fps", "tick_rate", "gen_duration"] {
+            overrides.insert(key.to_owned(), toml::Value::Integer(0));
+            assert!(matches!(
+                Settings::default().with_overrides(&overrides),
+                Err(SettingsError::Invalid(_))
+            ));
+            overrides.clear();
         }
     }
+
+    #[test]
+    fn overflowing_generations_and_empty_worlds_are_rejected() {
+        let mut settings = Settings::default();
+        settings.set_tick_rate(100_000);
+        settings.set_gen_duration(100_000);
+        assert!(matches!(
+            settings.validate(),
+            Err(SettingsError::Invalid(_))
+        ));
+        for size in [(0.0, 960.0), (960.0, -1.0), (f32::NAN, 960.0)] {
+            let mut settings = Settings::default();
+            settings.set_world_size(size);
+            assert!(matches!(
+                settings.validate(),
+                Err(SettingsError::Invalid(_))
+            ));
+            settings = Settings::default();
+            settings.set_screen_size(size);
+            assert!(matches!(
+                settings.validate(),
+                Err(SettingsError::Invalid(_))
+            ));
+        }
+        // Windows larger than the map shrink to it
+        let mut settings = Settings::default();
+        settings.set_screen_size((4000.0, 100.0));
+        assert_eq!(settings.screen_size(), (960.0, 100.0));
+    }
+
+    #[test]
+    fn species_roundtrip() {
+        let mut settings = Settings::default();
+        settings.set_seed(Some(42));
+        let mut fast = Species::new("fast_small");
+        fast.set_start_blobs(Some(4));
+        fast.set_tint(Some((1.0, 0.4, 0.4)));
+        fast.genes_mut().insert(
+            "speed".to_owned(),
+            GeneOverride {
+                base: Some(1.5),
+                ..GeneOverride::default()
+            },
+        );
+        let mut slow = Species::new("slow_large");
+        slow.set_mutation(Some(Mutation::Gaussian));
+        slow.set_selection(Some(Selection::default()));
+        slow.genes_mut().insert(
+            "size".to_owned(),
+            GeneOverride {
+                base: Some(1.4),
+                min: Some(0.5),
+                ..GeneOverride::default()
+            },
+        );
+        settings.set_species(vec![fast, slow]);
+        let (loaded, text) = roundtrip(&settings, "species");
+        assert_eq!(text.matches("[[species]]").count(), 2);
+        assert!(loaded == settings);
+        assert_eq!(loaded.species_settings().unwrap().len(), 2);
+    }
 }
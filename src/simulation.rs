@@ -0,0 +1,615 @@
+//! blob-sim simulation core
+//!
+
+// ============================================================================
+// Imports
+// ============================================================================
+
+use super::blobs::{self, index::BlobIndex, Blob, BlobState, Claim, Target};
+use super::food::{FoodGrid, FoodId};
+use super::lineage::Lineage;
+use super::settings::{Reproduction, Settings, SettingsError};
+use super::stats::{GenerationStats, SpeciesStats};
+use super::TILE_SIZE;
+use ggez::nalgebra::Point2;
+use rand::{Rng, SeedableRng};
+use rand_pcg::Pcg64;
+use rayon::prelude::*;
+use serde::{Deserialize, Serialize};
+
+// ============================================================================
+// Types
+// ============================================================================
+
+/// RNG driving every random decision of a simulation
+pub type SimulationRng = Pcg64;
+
+// ============================================================================
+// Seeds
+// ============================================================================
+
+/// Largest seed, TOML integers are signed so larger ones could not be saved
+/// with the settings of a run
+pub const MAX_SEED: u64 = i64::MAX as u64;
+
+/// Random seed for runs without one, at most [`MAX_SEED`]
+pub fn random_seed() -> u64 {
+    rand::random::<u64>() & MAX_SEED
+}
+
+// ============================================================================
+// Simulation Core
+// ============================================================================
+
+/// Window independent simulation state
+///
+/// Holds the blobs, the food and the settings of a running world and
+/// advances them tick by tick. Nothing in here needs a `ggez::Context`, so
+/// the core can be driven headless from tests or batch jobs.
+///
+/// All randomness is drawn from a single RNG seeded from the settings, so
+/// two cores created with equal settings and seed evolve identically. Blobs
+/// are updated in parallel, each with its own RNG seeded from the simulation
+/// RNG, so the outcome does not depend on the number of threads either.
+///
+/// Every blob moves, eats and reproduces by the settings of its species,
+/// which are derived from the settings of the run once on creation.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct SimulationCore {
+    // State
+    blobs: Vec<Blob>,
+    next_id: usize,
+    food: FoodGrid,
+    generation: u32,
+    generation_frames: u32,
+    food_per_gen: u32,
+    food_placed: usize,
+    /// Blobs of every species eaten by other blobs in the current
+    /// generation
+    eaten: Vec<usize>,
+    lineage: Lineage,
+    // Randomness
+    seed: u64,
+    rng: SimulationRng,
+    // Settings
+    settings: Settings,
+    species: Vec<Settings>,
+}
+
+impl SimulationCore {
+    /// Core running `settings`, which have to pass [`Settings::validate`]
+    pub fn new(mut settings: Settings) -> Result<SimulationCore, SettingsError> {
+        let seed = settings.seed().unwrap_or_else(random_seed);
+        settings.set_seed(Some(seed));
+        settings.validate()?;
+        let species = settings.species_settings()?;
+        let mut core = SimulationCore {
+            blobs: vec![],
+            next_id: 0,
+            food: FoodGrid::new(settings.world_size(), TILE_SIZE),
+            generation: 0,
+            generation_frames: 0,
+            food_per_gen: settings.start_food(),
+            food_placed: 0,
+            eaten: vec![0; species.len()],
+            lineage: Lineage::new(),
+            seed,
+            rng: SimulationRng::seed_from_u64(seed),
+            settings,
+            species,
+        };
+        core.reset(true);
+        Ok(core)
+    }
+
+    /// Checks that the state fits the settings, done when restoring a
+    /// snapshot
+    ///
+    /// Every blob has to belong to one of the species and carry a value for
+    /// every gene.
+    pub fn validate(&self) -> Result<(), String> {
+        if !self
+            .settings
+            .species_settings()
+            .is_ok_and(|species| species == self.species)
+            || self.eaten.len() != self.species.len()
+        {
+            return Err("species do not fit the settings".to_owned());
+        }
+        if !self.blobs.windows(2).all(|b| b[0].id() < b[1].id()) {
+            return Err("blobs are not ordered by id".to_owned());
+        }
+        let genes = self.settings.genes().len();
+        for blob in &self.blobs {
+            if blob.species() >= self.species.len() {
+                return Err(format!(
+                    "blob {} is of unknown species {}",
+                    blob.id(),
+                    blob.species()
+                ));
+            }
+            if blob.genome().values().len() != genes {
+                return Err(format!(
+                    "blob {} has {} genes instead of {}",
+                    blob.id(),
+                    blob.genome().values().len(),
+                    genes
+                ));
+            }
+        }
+        Ok(())
+    }
+
+    pub fn reset(&mut self, blobs: bool) {
+        self.eaten = vec![0; self.species.len()];
+        self.food.clear();
+        self.generation_frames = 0;
+        if blobs {
+            self.generation = 0;
+            self.food_per_gen = self.settings.start_food();
+            self.rng = SimulationRng::seed_from_u64(self.seed);
+            self.blobs = vec![];
+            self.next_id = 0;
+            self.lineage.clear();
+            for (species, settings) in self.species.iter().enumerate() {
+                for _ in 0..settings.start_blobs() {
+                    let blob = Blob::new(self.next_id, species, settings, &mut self.rng);
+                    self.lineage.born(&blob);
+                    self.blobs.push(blob);
+                    self.next_id += 1;
+                }
+            }
+        }
+        let world_size = self.settings.world_size();
+        for _ in 0..self.food_per_gen {
+            self.food.insert(Point2::new(
+                self.rng.gen::<f32>() * world_size.0,
+                self.rng.gen::<f32>() * world_size.1,
+            ));
+        }
+        self.food_placed = self.food.len();
+        if !blobs {
+            self.food_per_gen = self.settings.decay_food(self.food_per_gen);
+        }
+    }
+
+    /// Advances the simulation by a single tick
+    ///
+    /// Returns the statistics of the finished generation if this tick ended
+    /// it.
+    pub fn step(&mut self) -> Option<GenerationStats> {
+        self.generation_frames += 1;
+        if self.generation_frames > self.generation_length() {
+            Some(self.next_generation())
+        } else {
+            // Let every blob move and claim food or prey...
+            let tick_seed = self.rng.gen::<u64>();
+            let index = if self.settings.predation()
+                || self.settings.reproduction() == Reproduction::Sexual
+            {
+                Some(BlobIndex::new(
+                    &self.blobs,
+                    self.settings.world_size(),
+                    TILE_SIZE,
+                ))
+            } else {
+                None
+            };
+            let (food, species) = (&self.food, &self.species);
+            let claims: Vec<Option<Claim>> = self
+                .blobs
+                .par_iter_mut()
+                .map(|blob| {
+                    let mut rng = SimulationRng::seed_from_u64(tick_seed ^ blob.id() as u64);
+                    blob.update(food, index.as_ref(), &species[blob.species()], &mut rng)
+                })
+                .collect();
+            // ...hand out the claimed prey and food...
+            self.resolve_claims(claims);
+            // ...and send the blobs home that are done
+            let species = &self.species;
+            self.blobs
+                .par_iter_mut()
+                .for_each(|blob| blob.check_home(&species[blob.species()]));
+            None
+        }
+    }
+
+    /// Hands out the claimed prey, then the claimed food, pairs up mates and
+    /// removes the eaten blobs
+    ///
+    /// Hunts succeed in order of arrival, as long as neither hunter nor prey
+    /// has been eaten yet and the prey did not make it home. Hunters arriving
+    /// at the same time draw lots. Food claimed by eaten blobs stays in place.
+    /// Mates pair up in order of arrival too, if both are still looking.
+    fn resolve_claims(&mut self, claims: Vec<Option<Claim>>) {
+        let mut eaten = vec![false; self.blobs.len()];
+        let mut hunts: Vec<(usize, usize, f32, u64)> = vec![];
+        let mut meetings: Vec<(usize, usize, f32, u64)> = vec![];
+        let mut food_claims: Vec<(usize, FoodId, f32)> = vec![];
+        for (blob, claim) in claims.into_iter().enumerate() {
+            match claim {
+                Some(Claim {
+                    target: Target::Prey(prey),
+                    arrival,
+                }) => hunts.push((blob, prey, arrival, self.rng.gen())),
+                Some(Claim {
+                    target: Target::Food(food),
+                    arrival,
+                }) => food_claims.push((blob, food, arrival)),
+                Some(Claim {
+                    target: Target::Mate(mate),
+                    arrival,
+                }) => meetings.push((blob, mate, arrival, self.rng.gen())),
+                None => {}
+            }
+        }
+        hunts.sort_by(|a, b| a.2.total_cmp(&b.2).then(a.3.cmp(&b.3)));
+        for (hunter, prey, _, _) in hunts {
+            if eaten[hunter] {
+                continue;
+            }
+            match self.blobs.binary_search_by_key(&prey, Blob::id) {
+                Ok(prey) if !eaten[prey] && self.blobs[prey].state() != BlobState::AtHome => {
+                    let prey_blob = self.blobs[prey].clone();
+                    self.blobs[hunter].eat_prey(&prey_blob);
+                    eaten[prey] = true;
+                }
+                _ => self.blobs[hunter].missed_food(),
+            }
+        }
+        food_claims.retain(|(blob, _, _)| !eaten[*blob]);
+        self.resolve_food(food_claims);
+        meetings.sort_by(|a, b| a.2.total_cmp(&b.2).then(a.3.cmp(&b.3)));
+        for (blob, mate, _, _) in meetings {
+            let looking = |b: &Blob| b.state() == BlobState::SeekMate && b.mate().is_none();
+            if eaten[blob] || !looking(&self.blobs[blob]) {
+                continue;
+            }
+            if let Ok(mate) = self.blobs.binary_search_by_key(&mate, Blob::id) {
+                if !eaten[mate] && looking(&self.blobs[mate]) {
+                    let (a, b) = (self.blobs[blob].clone(), self.blobs[mate].clone());
+                    self.blobs[blob].pair(&b);
+                    self.blobs[mate].pair(&a);
+                }
+            }
+        }
+        let count = eaten.iter().filter(|&&e| e).count();
+        for (blob, _) in self.blobs.iter().zip(&eaten).filter(|(_, &e)| e) {
+            self.lineage.died(blob.id(), self.generation);
+            self.eaten[blob.species()] += 1;
+        }
+        if count > 0 {
+            let mut eaten = eaten.into_iter();
+            self.blobs.retain(|_| !eaten.next().unwrap());
+        }
+    }
+
+    /// Gives contested food to the blob that arrived first in the tick
+    ///
+    /// Blobs arriving at the same time draw lots, so neither the order of the
+    /// blobs nor the thread scheduling favours anyone.
+    fn resolve_food(&mut self, mut claims: Vec<(usize, FoodId, f32)>) {
+        claims.sort_by(|a, b| a.1.cmp(&b.1).then(a.2.total_cmp(&b.2)).then(a.0.cmp(&b.0)));
+        for contenders in claims.chunk_by(|a, b| a.1 == b.1) {
+            let first = contenders[0].2;
+            let ties = contenders
+                .iter()
+                .take_while(|(_, _, arrival)| *arrival == first)
+                .count();
+            let winner = if ties > 1 {
+                self.rng.gen_range(0, ties)
+            } else {
+                0
+            };
+            for (i, (blob, food, _)) in contenders.iter().enumerate() {
+                if i == winner {
+                    let species = self.blobs[*blob].species();
+                    self.blobs[*blob].eat(&self.species[species]);
+                    self.food.remove(*food);
+                } else {
+                    self.blobs[*blob].missed_food();
+                }
+            }
+        }
+    }
+
+    /// Advances the simulation until the current generation has ended
+    pub fn run_generation(&mut self) -> GenerationStats {
+        loop {
+            if let Some(stats) = self.step() {
+                return stats;
+            }
+        }
+    }
+
+    fn next_generation(&mut self) -> GenerationStats {
+        let mut new_blobs = vec![];
+        let mut births = vec![0; self.species.len()];
+        let mut starvations = vec![0; self.species.len()];
+        let blobs = std::mem::take(&mut self.blobs);
+        let sexual = self.settings.reproduction() == Reproduction::Sexual;
+        for mut blob in blobs {
+            let mate = blob.mate().cloned();
+            let species = blob.species();
+            match blob.next_gen(&self.species[species], &mut self.rng) {
+                blobs::GenerationResult::Starve => {
+                    self.lineage.died(blob.id(), self.generation);
+                    starvations[species] += 1
+                }
+                // Without a mate there are no offspring
+                blobs::GenerationResult::Reproduce(_) if sexual && mate.is_none() => {
+                    self.blobs.push(blob)
+                }
+                blobs::GenerationResult::Reproduce(offspring) => {
+                    for _ in 0..offspring {
+                        let child = Blob::evolve(
+                            self.next_id,
+                            self.generation + 1,
+                            &blob,
+                            mate.as_ref(),
+                            &self.species[species],
+                            &mut self.rng,
+                        );
+                        self.lineage.born(&child);
+                        new_blobs.push(child);
+                        self.next_id += 1;
+                    }
+                    births[species] += offspring as usize;
+                    self.blobs.push(blob);
+                }
+                blobs::GenerationResult::Live => self.blobs.push(blob),
+            }
+        }
+        self.blobs.append(&mut new_blobs);
+        let mut stats = GenerationStats::new(
+            self.seed,
+            self.generation,
+            &self.blobs,
+            self.settings.genes(),
+            births.iter().sum(),
+            (starvations.iter().sum(), self.eaten.iter().sum()),
+            (self.food_placed, self.food_placed - self.food.len()),
+        );
+        for (i, species) in self.settings.species().iter().enumerate() {
+            stats.species.push(SpeciesStats::new(
+                species.name(),
+                i,
+                &self.blobs,
+                self.settings.genes(),
+                births[i],
+                (starvations[i], self.eaten[i]),
+            ));
+        }
+        self.reset(false);
+        self.generation += 1;
+        stats
+    }
+
+    // State
+    /// All living blobs, ordered by id
+    #[inline(always)]
+    pub fn blobs(&self) -> &[Blob] {
+        &self.blobs
+    }
+    /// Living blob with `id`
+    pub fn blob(&self, id: usize) -> Option<&Blob> {
+        self.blobs
+            .binary_search_by_key(&id, Blob::id)
+            .ok()
+            .map(|i| &self.blobs[i])
+    }
+    /// Every blob of the run, living or dead
+    #[inline(always)]
+    pub fn lineage(&self) -> &Lineage {
+        &self.lineage
+    }
+    #[inline(always)]
+    pub fn food(&self) -> &FoodGrid {
+        &self.food
+    }
+    #[inline(always)]
+    pub fn generation(&self) -> u32 {
+        self.generation
+    }
+    #[inline(always)]
+    pub fn generation_frames(&self) -> u32 {
+        self.generation_frames
+    }
+    /// Amount of food placed at the start of the current generation
+    #[inline(always)]
+    pub fn food_per_gen(&self) -> u32 {
+        self.food_per_gen
+    }
+    /// Amount of food actually placed in the current generation
+    #[inline(always)]
+    pub fn food_placed(&self) -> usize {
+        self.food_placed
+    }
+    /// Number of ticks blobs get to move in each generation
+    #[inline(always)]
+    pub fn generation_length(&self) -> u32 {
+        self.settings().tick_rate() * self.settings().gen_duration()
+    }
+
+    // Randomness
+    /// Seed the RNG was created from, used again on a full reset
+    #[inline(always)]
+    pub fn seed(&self) -> u64 {
+        self.seed
+    }
+
+    // Settings
+    /// Settings of the run, with the seed set to the one actually used
+    #[inline(always)]
+    pub fn settings(&self) -> &Settings {
+        &self.settings
+    }
+    /// Settings of every species, indexed like [`Settings::species`] or a
+    /// single entry without species
+    #[inline(always)]
+    pub fn species_settings(&self) -> &[Settings] {
+        &self.species
+    }
+}
+
+// ============================================================================
+// Testing
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::genome::{Gene, Genes};
+    use crate::snapshot::{self, SnapshotError};
+    use crate::species::{GeneOverride, Species};
+    use std::convert::TryFrom;
+
+    const GENERATIONS: usize = 5;
+
+    fn settings(seed: u64) -> Settings {
+        let mut settings = Settings::default();
+        settings.set_seed(Some(seed));
+        settings
+    }
+
+    /// Settings using every kind of claim, food, prey and mates
+    fn claiming_settings(seed: u64) -> Settings {
+        let mut settings = settings(seed);
+        settings.set_start_blobs(16);
+        settings.set_predation(true);
+        settings.set_reproduction(Reproduction::Sexual);
+        settings
+    }
+
+    fn run(settings: Settings) -> Vec<GenerationStats> {
+        let mut core = SimulationCore::new(settings).unwrap();
+        (0..GENERATIONS).map(|_| core.run_generation()).collect()
+    }
+
+    fn run_with_threads(settings: Settings, threads: usize) -> Vec<GenerationStats> {
+        rayon::ThreadPoolBuilder::new()
+            .num_threads(threads)
+            .build()
+            .unwrap()
+            .install(|| run(settings))
+    }
+
+    /// Core with `blobs` fresh blobs in the field and no food
+    fn empty_core(blobs: usize) -> SimulationCore {
+        let mut settings = settings(3);
+        settings.set_start_blobs(blobs as u32);
+        settings.set_food_decay((0, 0, 0));
+        SimulationCore::new(settings).unwrap()
+    }
+
+    fn food_claim(food: FoodId, arrival: f32) -> Option<Claim> {
+        Some(Claim {
+            target: Target::Food(food),
+            arrival,
+        })
+    }
+
+    #[test]
+    fn contested_food_goes_to_one_claimant() {
+        for arrivals in [[0.5, 0.2, 0.8], [0.5, 0.5, 0.5]] {
+            let mut core = empty_core(3);
+            let food = core.food.insert(Point2::new(10.0, 10.0));
+            core.resolve_claims(arrivals.iter().map(|&a| food_claim(food, a)).collect());
+            let fed: Vec<u8> = core.blobs().iter().map(Blob::food_found).collect();
+            assert_eq!(fed.iter().filter(|&&f| f == 1).count(), 1);
+            assert_eq!(fed.iter().filter(|&&f| f == 0).count(), 2);
+            assert!(!core.food().contains(food));
+            if arrivals[0] != arrivals[1] {
+                // First come, first served
+                assert_eq!(fed, vec![0, 1, 0]);
+            }
+        }
+    }
+
+    #[test]
+    fn eaten_claimant_does_not_keep_food() {
+        let mut core = empty_core(2);
+        let food = core.food.insert(Point2::new(10.0, 10.0));
+        let prey = core.blobs()[1].id();
+        core.resolve_claims(vec![
+            Some(Claim {
+                target: Target::Prey(prey),
+                arrival: 0.9,
+            }),
+            food_claim(food, 0.1),
+        ]);
+        assert_eq!(core.blobs().len(), 1);
+        assert!(core.blob(prey).is_none());
+        assert_eq!(core.blobs()[0].food_found(), 1);
+        assert!(core.food().contains(food));
+        assert_eq!(core.eaten.iter().sum::<usize>(), 1);
+    }
+
+    #[test]
+    fn unfitting_species_are_an_error() {
+        let mut species = Species::new("odd");
+        species
+            .genes_mut()
+            .insert("wings".to_owned(), GeneOverride::default());
+        let mut settings = settings(1);
+        settings.set_species(vec![species]);
+        assert!(matches!(
+            SimulationCore::new(settings),
+            Err(SettingsError::Invalid(_))
+        ));
+    }
+
+    #[test]
+    fn inconsistent_snapshot_is_rejected() {
+        let invalid = |core: &SimulationCore| {
+            let mut bytes = vec![];
+            snapshot::write(core, &mut bytes).unwrap();
+            matches!(
+                snapshot::read(&mut bytes.as_slice()),
+                Err(SnapshotError::Invalid(_))
+            )
+        };
+        // Blobs of a species that is gone
+        let mut settings = settings(2);
+        settings.set_species(vec![Species::new("a"), Species::new("b")]);
+        let mut core = SimulationCore::new(settings).unwrap();
+        assert!(core.validate().is_ok());
+        core.settings.set_species(vec![Species::new("a")]);
+        core.species.truncate(1);
+        core.eaten.truncate(1);
+        assert!(invalid(&core));
+        // Blobs lacking a gene
+        let mut core = empty_core(2);
+        let mut genes: Vec<_> = core.settings.genes().iter().cloned().collect();
+        genes.push(Gene::new("wings", 1.0, 0.1));
+        core.settings.set_genes(Genes::try_from(genes).unwrap());
+        core.species = core.settings.species_settings().unwrap();
+        assert!(invalid(&core));
+        // Settings that would not load
+        let mut core = empty_core(2);
+        core.settings.set_predation_ratio(0.5);
+        core.species = core.settings.species_settings().unwrap();
+        let mut bytes = vec![];
+        snapshot::write(&core, &mut bytes).unwrap();
+        assert!(matches!(
+            snapshot::read(&mut bytes.as_slice()),
+            Err(SnapshotError::Settings(_))
+        ));
+    }
+
+    #[test]
+    fn same_seed_same_stats() {
+        assert_eq!(run(settings(7)), run(settings(7)));
+        assert_eq!(run(claiming_settings(7)), run(claiming_settings(7)));
+    }
+
+    #[test]
+    fn thread_count_does_not_change_stats() {
+        for settings in [settings(11), claiming_settings(11)] {
+            let single = run_with_threads(settings.clone(), 1);
+            assert_eq!(single, run_with_threads(settings.clone(), 2));
+            assert_eq!(single, run_with_threads(settings, 4));
+        }
+    }
+}
@@ -0,0 +1,192 @@
+//! blob-sim snapshots
+//!
+//! A snapshot file starts with [`MAGIC`] and the format [`VERSION`], followed
+//! by the bincode encoded [`SimulationCore`]: every blob, the food, the
+//! lineage, the generation counters, the settings and the RNG state.
+//! Restoring a snapshot continues the run exactly where it was saved, a
+//! snapshot whose state does not fit its settings is rejected.
+
+// ============================================================================
+// Imports
+// ============================================================================
+
+use super::settings::SettingsError;
+use super::simulation::SimulationCore;
+use std::{
+    error, fmt,
+    fs::File,
+    io::{self, BufReader, BufWriter, Read, Write},
+    path::Path,
+};
+
+// ============================================================================
+// Constants
+// ============================================================================
+
+pub const MAGIC: [u8; 8] = *b"BLOBSIM\0";
+pub const VERSION: u32 = 13;
+
+// ============================================================================
+// Save & Load
+// ============================================================================
+
+pub fn save<P: AsRef<Path>>(core: &SimulationCore, path: P) -> Result<(), SnapshotError> {
+    let mut writer = BufWriter::new(File::create(path)?);
+    write(core, &mut writer)?;
+    Ok(writer.flush()?)
+}
+
+pub fn load<P: AsRef<Path>>(path: P) -> Result<SimulationCore, SnapshotError> {
+    read(&mut BufReader::new(File::open(path)?))
+}
+
+pub fn write<W: Write>(core: &SimulationCore, writer: &mut W) -> Result<(), SnapshotError> {
+    writer.write_all(&MAGIC)?;
+    writer.write_all(&VERSION.to_le_bytes())?;
+    Ok(bincode::serialize_into(writer, core)?)
+}
+
+pub fn read<R: Read>(reader: &mut R) -> Result<SimulationCore, SnapshotError> {
+    let mut magic = [0; 8];
+    reader.read_exact(&mut magic)?;
+    if magic != MAGIC {
+        return Err(SnapshotError::Format);
+    }
+    let mut version = [0; 4];
+    reader.read_exact(&mut version)?;
+    match u32::from_le_bytes(version) {
+        VERSION => {
+            let core: SimulationCore = bincode::deserialize_from(reader)?;
+            core.settings().validate()?;
+            core.validate().map_err(SnapshotError::Invalid)?;
+            Ok(core)
+        }
+        version => Err(SnapshotError::Version(version)),
+    }
+}
+
+// ============================================================================
+// Snapshot Error
+// ============================================================================
+
+#[derive(Debug)]
+pub enum SnapshotError {
+    Io(io::Error),
+    Encoding(bincode::Error),
+    /// Not a snapshot file
+    Format,
+    /// Snapshot written by an incompatible version
+    Version(u32),
+    /// Settings of the snapshot that do not fit together
+    Settings(SettingsError),
+    /// State that does not fit the settings
+    Invalid(String),
+}
+
+impl fmt::Display for SnapshotError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            SnapshotError::Io(e) => write!(f, "snapshot io: {}", e),
+            SnapshotError::Encoding(e) => write!(f, "snapshot encoding: {}", e),
+            SnapshotError::Format => write!(f, "not a snapshot file"),
+            SnapshotError::Version(v) => write!(
+                f,
+                "snapshot version {} is not supported, expected {}",
+                v, VERSION
+            ),
+            SnapshotError::Settings(e) => write!(f, "snapshot {}", e),
+            SnapshotError::Invalid(e) => write!(f, "invalid snapshot: {}", e),
+        }
+    }
+}
+
+impl error::Error for SnapshotError {}
+
+impl From<io::Error> for SnapshotError {
+    fn from(e: io::Error) -> SnapshotError {
+        SnapshotError::Io(e)
+    }
+}
+
+impl From<bincode::Error> for SnapshotError {
+    fn from(e: bincode::Error) -> SnapshotError {
+        SnapshotError::Encoding(e)
+    }
+}
+
+impl From<SettingsError> for SnapshotError {
+    fn from(e: SettingsError) -> SnapshotError {
+        SnapshotError::Settings(e)
+    }
+}
+
+// ============================================================================
+// Testing
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::settings::Settings;
+
+    fn core() -> SimulationCore {
+        let mut settings = Settings::default();
+        settings.set_seed(Some(5));
+        settings.set_predation(true);
+        let mut core = SimulationCore::new(settings).unwrap();
+        core.run_generation();
+        // Stop in the middle of a generation
+        for _ in 0..100 {
+            core.step();
+        }
+        core
+    }
+
+    fn snapshot(core: &SimulationCore) -> Vec<u8> {
+        let mut bytes = vec![];
+        write(core, &mut bytes).unwrap();
+        bytes
+    }
+
+    #[test]
+    fn loaded_core_continues_like_the_original() {
+        let mut original = core();
+        let mut loaded = read(&mut snapshot(&original).as_slice()).unwrap();
+        assert_eq!(loaded.generation(), original.generation());
+        assert_eq!(loaded.generation_frames(), original.generation_frames());
+        for _ in 0..3 {
+            assert_eq!(loaded.run_generation(), original.run_generation());
+        }
+    }
+
+    #[test]
+    fn bad_magic_is_rejected() {
+        let mut bytes = snapshot(&core());
+        bytes[0] ^= 0xff;
+        assert!(matches!(
+            read(&mut bytes.as_slice()),
+            Err(SnapshotError::Format)
+        ));
+        assert!(matches!(read(&mut &b"blob"[..]), Err(SnapshotError::Io(_))));
+    }
+
+    #[test]
+    fn other_version_is_rejected() {
+        let mut bytes = snapshot(&core());
+        bytes[MAGIC.len()..MAGIC.len() + 4].copy_from_slice(&(VERSION + 1).to_le_bytes());
+        assert!(matches!(
+            read(&mut bytes.as_slice()),
+            Err(SnapshotError::Version(v)) if v == VERSION + 1
+        ));
+    }
+
+    #[test]
+    fn truncated_snapshot_is_rejected() {
+        let bytes = snapshot(&core());
+        let mut truncated = &bytes[..bytes.len() / 2];
+        assert!(matches!(
+            read(&mut truncated),
+            Err(SnapshotError::Encoding(_))
+        ));
+    }
+}
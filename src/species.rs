@@ -0,0 +1,179 @@
+//! blob-sim species
+//!
+//! A [`Species`] is a named group of blobs competing in the same world with
+//! its own starting traits, mutation, selection and look. Everything not set
+//! for a species is taken from the settings of the run, offspring always
+//! belong to the species of their parents.
+
+// ============================================================================
+// Imports
+// ============================================================================
+
+use super::genome::{mutation::Mutation, Gene};
+use super::selection::Selection;
+use super::settings::Settings;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+// ============================================================================
+// Species
+// ============================================================================
+
+/// Overrides of the settings for the blobs of one species
+///
+/// In a config file every species is a table in the `species` array:
+///
+/// ```toml
+/// [[species]]
+/// name = "fast_small"
+/// start_blobs = 8
+/// tint = [1.0, 0.4, 0.4]
+///
+/// [species.genes.speed]
+/// base = 1.5
+///
+/// [species.genes.size]
+/// base = 0.7
+/// mutation = 0.2
+/// ```
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Species {
+    name: String,
+    start_blobs: Option<u32>,
+    tint: Option<(f32, f32, f32)>,
+    sprite: Option<String>,
+    // Tables last as TOML writes them after plain values
+    mutation: Option<Mutation>,
+    selection: Option<Selection>,
+    #[serde(default)]
+    genes: BTreeMap<String, GeneOverride>,
+}
+
+impl Species {
+    pub fn new(name: &str) -> Species {
+        Species {
+            name: name.to_owned(),
+            start_blobs: None,
+            tint: None,
+            sprite: None,
+            mutation: None,
+            selection: None,
+            genes: BTreeMap::new(),
+        }
+    }
+
+    #[inline(always)]
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Blobs of the species in the first generation, `start_blobs` of the
+    /// settings if not set
+    #[inline(always)]
+    pub fn start_blobs(&self) -> Option<u32> {
+        self.start_blobs
+    }
+    #[inline(always)]
+    pub fn set_start_blobs(&mut self, blobs: Option<u32>) {
+        self.start_blobs = blobs;
+    }
+
+    // Look
+    /// Color the sprites are tinted with while not tinted by a gene
+    #[inline(always)]
+    pub fn tint(&self) -> Option<(f32, f32, f32)> {
+        self.tint
+    }
+    #[inline(always)]
+    pub fn set_tint(&mut self, tint: Option<(f32, f32, f32)>) {
+        self.tint = tint;
+    }
+    /// Path of the sprite image within the resources, the default blob if
+    /// not set
+    #[inline(always)]
+    pub fn sprite(&self) -> Option<&str> {
+        self.sprite.as_deref()
+    }
+    #[inline(always)]
+    pub fn set_sprite(&mut self, sprite: Option<String>) {
+        self.sprite = sprite;
+    }
+
+    // Evolution
+    #[inline(always)]
+    pub fn mutation(&self) -> Option<&Mutation> {
+        self.mutation.as_ref()
+    }
+    #[inline(always)]
+    pub fn set_mutation(&mut self, mutation: Option<Mutation>) {
+        self.mutation = mutation;
+    }
+    #[inline(always)]
+    pub fn selection(&self) -> Option<&Selection> {
+        self.selection.as_ref()
+    }
+    #[inline(always)]
+    pub fn set_selection(&mut self, selection: Option<Selection>) {
+        self.selection = selection;
+    }
+    /// Overrides of the genes, by gene name
+    #[inline(always)]
+    pub fn genes(&self) -> &BTreeMap<String, GeneOverride> {
+        &self.genes
+    }
+    #[inline(always)]
+    pub fn genes_mut(&mut self) -> &mut BTreeMap<String, GeneOverride> {
+        &mut self.genes
+    }
+
+    /// Applies the overrides of the species to `settings`
+    ///
+    /// Species can only change genes the settings have, as all blobs share
+    /// one genome layout.
+    pub fn apply(&self, settings: &mut Settings) -> Result<(), String> {
+        if let Some(blobs) = self.start_blobs {
+            settings.set_start_blobs(blobs);
+        }
+        if let Some(mutation) = &self.mutation {
+            settings.set_mutation(mutation.clone());
+        }
+        if let Some(selection) = &self.selection {
+            settings.set_selection(selection.clone());
+        }
+        for (name, gene) in &self.genes {
+            match settings.gene_mut(name) {
+                Some(g) => gene.apply(g),
+                None => return Err(format!("species {} has unknown gene {}", self.name, name)),
+            }
+        }
+        Ok(())
+    }
+}
+
+// ============================================================================
+// Gene Override
+// ============================================================================
+
+/// Fields of a [`Gene`] a species sets differently, the others are kept
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct GeneOverride {
+    pub base: Option<f32>,
+    pub mutation: Option<f32>,
+    pub min: Option<f32>,
+    pub max: Option<f32>,
+}
+
+impl GeneOverride {
+    pub fn apply(&self, gene: &mut Gene) {
+        if let Some(base) = self.base {
+            gene.set_base(base);
+        }
+        if let Some(mutation) = self.mutation {
+            gene.set_mutation(mutation);
+        }
+        let (min, max) = gene.bounds();
+        gene.set_bounds((self.min.or(min), self.max.or(max)));
+    }
+}
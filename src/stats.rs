@@ -0,0 +1,393 @@
+//! blob-sim statistics
+//!
+
+// ============================================================================
+// Imports
+// ============================================================================
+
+use super::blobs::Blob;
+use super::genome::Genes;
+use serde::Serialize;
+use std::{
+    fmt,
+    fs::File,
+    io::{self, BufWriter, Write},
+    path::Path,
+    str::FromStr,
+};
+
+// ============================================================================
+// Generation Stats
+// ============================================================================
+
+/// Outcome of a single generation
+///
+/// `population` and the trait statistics describe the blobs alive after
+/// selection, i.e. the `survivors` plus the `births` entering the next
+/// generation. There are trait statistics for every gene, in the order of the
+/// genes. With species the same numbers are broken down by species, in the
+/// order of the settings.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct GenerationStats {
+    pub seed: u64,
+    pub generation: u32,
+    pub population: usize,
+    pub births: usize,
+    pub starvations: usize,
+    /// Blobs eaten by other blobs
+    pub eaten: usize,
+    pub survivors: usize,
+    pub food_placed: usize,
+    pub food_eaten: usize,
+    pub traits: Vec<TraitStats>,
+    pub species: Vec<SpeciesStats>,
+}
+
+impl GenerationStats {
+    /// `deaths` are the blobs starved and eaten, `food` the food placed and
+    /// eaten
+    pub fn new(
+        seed: u64,
+        generation: u32,
+        blobs: &[Blob],
+        genes: &Genes,
+        births: usize,
+        deaths: (usize, usize),
+        food: (usize, usize),
+    ) -> GenerationStats {
+        GenerationStats {
+            seed,
+            generation,
+            population: blobs.len(),
+            births,
+            starvations: deaths.0,
+            eaten: deaths.1,
+            survivors: blobs.len() - births,
+            food_placed: food.0,
+            food_eaten: food.1,
+            traits: genes
+                .iter()
+                .enumerate()
+                .map(|(i, gene)| TraitStats::new(gene.name(), blobs.iter().map(|b| b.genome()[i])))
+                .collect(),
+            species: vec![],
+        }
+    }
+
+    /// Statistics of the trait called `name`
+    pub fn trait_stats(&self, name: &str) -> Option<&TraitStats> {
+        self.traits.iter().find(|t| t.name == name)
+    }
+
+    fn trait_mean(&self, name: &str) -> f32 {
+        self.trait_stats(name).map_or(f32::NAN, |t| t.mean)
+    }
+}
+
+impl fmt::Display for GenerationStats {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "generation: {}, speed: {}, sense: {}, size: {}, blobs: {}, food: {}/{}",
+            self.generation,
+            self.trait_mean("speed"),
+            self.trait_mean("sense"),
+            self.trait_mean("size"),
+            self.population,
+            self.food_eaten,
+            self.food_placed
+        )?;
+        for species in &self.species {
+            write!(f, ", {}: {}", species.name, species.population)?;
+        }
+        Ok(())
+    }
+}
+
+// ============================================================================
+// Species Stats
+// ============================================================================
+
+/// Outcome of a single generation for one species
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct SpeciesStats {
+    pub name: String,
+    pub population: usize,
+    pub births: usize,
+    pub starvations: usize,
+    pub eaten: usize,
+    pub traits: Vec<TraitStats>,
+}
+
+impl SpeciesStats {
+    /// Statistics of the blobs of the `species` with index `species`,
+    /// `deaths` are its blobs starved and eaten
+    pub fn new(
+        name: &str,
+        species: usize,
+        blobs: &[Blob],
+        genes: &Genes,
+        births: usize,
+        deaths: (usize, usize),
+    ) -> SpeciesStats {
+        let blobs: Vec<&Blob> = blobs.iter().filter(|b| b.species() == species).collect();
+        SpeciesStats {
+            name: name.to_owned(),
+            population: blobs.len(),
+            births,
+            starvations: deaths.0,
+            eaten: deaths.1,
+            traits: genes
+                .iter()
+                .enumerate()
+                .map(|(i, gene)| TraitStats::new(gene.name(), blobs.iter().map(|b| b.genome()[i])))
+                .collect(),
+        }
+    }
+}
+
+// ============================================================================
+// Trait Stats
+// ============================================================================
+
+/// Distribution of a single trait over a population, `NaN` if it is empty
+///
+/// `NaN` is written as an empty field to CSV and as `null` to JSON Lines.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct TraitStats {
+    pub name: String,
+    pub mean: f32,
+    pub min: f32,
+    pub max: f32,
+    pub stddev: f32,
+}
+
+impl TraitStats {
+    pub fn new<I: Iterator<Item = f32>>(name: &str, values: I) -> TraitStats {
+        let values: Vec<f32> = values.collect();
+        let count = values.len() as f32;
+        let mean = values.iter().sum::<f32>() / count;
+        let variance = values.iter().map(|v| (v - mean).powi(2)).sum::<f32>() / count;
+        TraitStats {
+            name: name.to_owned(),
+            mean,
+            min: values.iter().copied().fold(f32::NAN, f32::min),
+            max: values.iter().copied().fold(f32::NAN, f32::max),
+            stddev: variance.sqrt(),
+        }
+    }
+}
+
+// ============================================================================
+// Stats Format
+// ============================================================================
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum StatsFormat {
+    Csv,
+    JsonLines,
+}
+
+impl StatsFormat {
+    #[inline(always)]
+    pub fn extension(self) -> &'static str {
+        match self {
+            StatsFormat::Csv => "csv",
+            StatsFormat::JsonLines => "jsonl",
+        }
+    }
+}
+
+impl FromStr for StatsFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<StatsFormat, String> {
+        match s {
+            "csv" => Ok(StatsFormat::Csv),
+            "jsonl" | "json" => Ok(StatsFormat::JsonLines),
+            _ => Err(format!("unknown stats format {}, use csv or jsonl", s)),
+        }
+    }
+}
+
+// ============================================================================
+// Stats Recorder
+// ============================================================================
+
+/// Writes one row per generation as CSV or JSON Lines
+pub struct StatsRecorder<W: Write> {
+    writer: W,
+    format: StatsFormat,
+    header: bool,
+}
+
+impl StatsRecorder<BufWriter<File>> {
+    pub fn create<P: AsRef<Path>>(path: P, format: StatsFormat) -> io::Result<Self> {
+        Ok(StatsRecorder::new(
+            BufWriter::new(File::create(path)?),
+            format,
+        ))
+    }
+}
+
+impl<W: Write> StatsRecorder<W> {
+    pub fn new(writer: W, format: StatsFormat) -> StatsRecorder<W> {
+        StatsRecorder {
+            writer,
+            format,
+            header: false,
+        }
+    }
+
+    pub fn record(&mut self, stats: &GenerationStats) -> io::Result<()> {
+        match self.format {
+            StatsFormat::Csv => self.record_csv(stats),
+            StatsFormat::JsonLines => {
+                serde_json::to_writer(&mut self.writer, stats)?;
+                writeln!(self.writer)
+            }
+        }
+    }
+
+    fn record_csv(&mut self, stats: &GenerationStats) -> io::Result<()> {
+        if !self.header {
+            write!(
+                self.writer,
+                "seed,generation,population,births,starvations,eaten,survivors,food_placed,food_eaten"
+            )?;
+            for t in &stats.traits {
+                for column in ["mean", "min", "max", "stddev"] {
+                    write!(
+                        self.writer,
+                        ",{}",
+                        csv_field(&format!("{}_{}", t.name, column))
+                    )?;
+                }
+            }
+            for s in &stats.species {
+                for column in ["population", "births", "starvations", "eaten"] {
+                    write!(
+                        self.writer,
+                        ",{}",
+                        csv_field(&format!("{}_{}", s.name, column))
+                    )?;
+                }
+                for t in &s.traits {
+                    for column in ["mean", "stddev"] {
+                        let name = format!("{}_{}_{}", s.name, t.name, column);
+                        write!(self.writer, ",{}", csv_field(&name))?;
+                    }
+                }
+            }
+            writeln!(self.writer)?;
+            self.header = true;
+        }
+        write!(
+            self.writer,
+            "{},{},{},{},{},{},{},{},{}",
+            stats.seed,
+            stats.generation,
+            stats.population,
+            stats.births,
+            stats.starvations,
+            stats.eaten,
+            stats.survivors,
+            stats.food_placed,
+            stats.food_eaten
+        )?;
+        for t in &stats.traits {
+            for value in [t.mean, t.min, t.max, t.stddev] {
+                write!(self.writer, ",{}", csv_number(value))?;
+            }
+        }
+        for s in &stats.species {
+            write!(
+                self.writer,
+                ",{},{},{},{}",
+                s.population, s.births, s.starvations, s.eaten
+            )?;
+            for t in &s.traits {
+                write!(
+                    self.writer,
+                    ",{},{}",
+                    csv_number(t.mean),
+                    csv_number(t.stddev)
+                )?;
+            }
+        }
+        writeln!(self.writer)
+    }
+
+    pub fn flush(&mut self) -> io::Result<()> {
+        self.writer.flush()
+    }
+}
+
+// ============================================================================
+// Helpers
+// ============================================================================
+
+/// `field` quoted for CSV if it contains a separator, quote or line break
+pub fn csv_field(field: &str) -> String {
+    if field.contains([',', '"', '\n']) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_owned()
+    }
+}
+
+/// `value` for CSV, empty for `NaN`, e.g. the traits of an empty population
+pub fn csv_number(value: f32) -> String {
+    if value.is_nan() {
+        String::new()
+    } else {
+        value.to_string()
+    }
+}
+
+// ============================================================================
+// Testing
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn stats() -> GenerationStats {
+        let empty = || TraitStats::new("speed", std::iter::empty());
+        GenerationStats {
+            seed: 1,
+            generation: 0,
+            population: 0,
+            births: 0,
+            starvations: 2,
+            eaten: 0,
+            survivors: 0,
+            food_placed: 10,
+            food_eaten: 3,
+            traits: vec![empty()],
+            species: vec![SpeciesStats {
+                name: "fast, \"small\"".to_owned(),
+                population: 0,
+                births: 0,
+                starvations: 2,
+                eaten: 0,
+                traits: vec![empty()],
+            }],
+        }
+    }
+
+    #[test]
+    fn csv_escapes_names_and_leaves_empty_traits_blank() {
+        let mut recorder = StatsRecorder::new(vec![], StatsFormat::Csv);
+        recorder.record(&stats()).unwrap();
+        let csv = String::from_utf8(recorder.writer).unwrap();
+        let lines: Vec<&str> = csv.lines().collect();
+        assert!(lines[0].ends_with(
+            ",\"fast, \"\"small\"\"_population\",\"fast, \"\"small\"\"_births\",\
+             \"fast, \"\"small\"\"_starvations\",\"fast, \"\"small\"\"_eaten\",\
+             \"fast, \"\"small\"\"_speed_mean\",\"fast, \"\"small\"\"_speed_stddev\""
+        ));
+        assert_eq!(lines[1], "1,0,0,0,2,0,0,10,3,,,,,0,0,2,0,,");
+    }
+}